@@ -0,0 +1,195 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+
+// the kernel's own limit (CONFIG_NR_CPUS), anything above is a typo and would only waste memory
+const MAX_CPUS: usize = 8192;
+
+/// A logical cpu the OS schedules on, an SMT sibling when its physical core runs several
+#[derive(Clone, Copy, Debug)]
+pub struct LogicalCpu {
+    pub id: usize,
+    pub core_id: usize,
+    pub package_id: usize,
+    /// None on machines without NUMA information
+    pub node: Option<usize>,
+}
+
+pub struct CpuTopology {
+    /// Online cpus only, sorted by id
+    pub cpus: Vec<LogicalCpu>,
+}
+
+impl CpuTopology {
+    /// Reads the topology from `/sys/devices/system/cpu`
+    #[cfg(target_os = "linux")]
+    pub fn detect() -> Result<CpuTopology, String> {
+        use std::fs;
+
+        const CPU_ROOT: &str = "/sys/devices/system/cpu";
+        let online = fs::read_to_string(format!("{CPU_ROOT}/online"))
+            .map_err(|e| format!("Could not read the online cpus: {e}"))?;
+        let read_id = |cpu: usize, file: &str| fs::read_to_string(format!("{CPU_ROOT}/cpu{cpu}/topology/{file}"))
+            .ok()
+            .and_then(|id| id.trim().parse::<usize>().ok());
+
+        let mut cpus = Vec::new();
+        for id in parse_cpu_list(&online)? {
+            let node = fs::read_dir(format!("{CPU_ROOT}/cpu{id}"))
+                .ok()
+                .and_then(|entries| entries.flatten().find_map(|entry| {
+                    entry.file_name().to_str()?.strip_prefix("node")?.parse::<usize>().ok()
+                }));
+
+            cpus.push(LogicalCpu {
+                id,
+                core_id: read_id(id, "core_id").unwrap_or(id),
+                package_id: read_id(id, "physical_package_id").unwrap_or(0),
+                node,
+            });
+        }
+
+        match cpus.is_empty() {
+            true => Err("No online cpus found".to_string()),
+            false => Ok(CpuTopology { cpus }),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn detect() -> Result<CpuTopology, String> {
+        Err("Thread pinning needs the cpu topology, which is only read on Linux".to_string())
+    }
+
+    /// The first logical cpu of every physical core, then the remaining SMT siblings
+    fn physical_cores_first(&self) -> Vec<usize> {
+        let mut cpus = self.cpus.clone();
+        cpus.sort_by_key(|cpu| (cpu.package_id, cpu.core_id, cpu.id));
+
+        let mut first = Vec::new();
+        let mut siblings = Vec::new();
+        let mut last_core = None;
+        for cpu in cpus {
+            match last_core == Some((cpu.package_id, cpu.core_id)) {
+                true => siblings.push(cpu.id),
+                false => first.push(cpu.id),
+            }
+            last_core = Some((cpu.package_id, cpu.core_id));
+        }
+        first.extend(siblings);
+        first
+    }
+
+    /// Every sibling of a physical core before moving on to the next core
+    fn siblings_together(&self) -> Vec<usize> {
+        let mut cpus = self.cpus.clone();
+        cpus.sort_by_key(|cpu| (cpu.package_id, cpu.core_id, cpu.id));
+        cpus.iter().map(|cpu| cpu.id).collect()
+    }
+}
+
+/// Where the workers of a CPU run are placed, worker `n` runs on the `n`th cpu of the resulting
+/// order. When there are more workers than cpus the order starts over.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PinningStrategy {
+    /// Spread over physical cores, SMT siblings are only used once every core has a worker
+    PhysicalCoresFirst,
+    /// Fill both siblings of a core before using the next, to stress SMT contention
+    SmtSiblings,
+    /// Exactly these logical cpus
+    Explicit(Vec<usize>),
+    /// Only the cpus of one NUMA node, physical cores first
+    NumaNode(usize),
+}
+
+impl PinningStrategy {
+    /// Returns the logical cpu for every worker
+    pub fn assign(&self, topology: &CpuTopology, threads: usize) -> Result<Vec<usize>, String> {
+        let order = match self {
+            PinningStrategy::PhysicalCoresFirst => topology.physical_cores_first(),
+            PinningStrategy::SmtSiblings => topology.siblings_together(),
+            PinningStrategy::Explicit(cpus) => {
+                if let Some(missing) = cpus.iter().find(|cpu| !topology.cpus.iter().any(|online| online.id == **cpu)) {
+                    return Err(format!("CPU {missing} does not exist or is offline"))
+                }
+                cpus.clone()
+            }
+            PinningStrategy::NumaNode(node) => {
+                let on_node = CpuTopology {
+                    cpus: topology.cpus.iter().filter(|cpu| cpu.node == Some(*node)).copied().collect(),
+                };
+                on_node.physical_cores_first()
+            }
+        };
+
+        if order.is_empty() {
+            return Err(format!("{self} has no cpus to pin to"))
+        }
+
+        Ok(order.into_iter().cycle().take(threads).collect())
+    }
+}
+
+impl Display for PinningStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinningStrategy::PhysicalCoresFirst => f.write_str("Physical cores first"),
+            PinningStrategy::SmtSiblings => f.write_str("SMT siblings"),
+            PinningStrategy::Explicit(cpus) => write!(f, "CPUs {}", format_cpu_list(cpus)),
+            PinningStrategy::NumaNode(node) => write!(f, "NUMA node {node}"),
+        }
+    }
+}
+
+/// Parses the kernel's cpu list format, e.g. `0-3,8,10-11`
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        let parse = |id: &str| match id.trim().parse::<usize>() {
+            Ok(cpu) if cpu < MAX_CPUS => Ok(cpu),
+            Ok(_) => Err(format!("{part} goes past the highest cpu Linux supports ({})", MAX_CPUS - 1)),
+            Err(_) => Err(format!("{part} is not a cpu or a range of cpus")),
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("{part} is reversed, write it as {end}-{start}"))
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(parse(part)?),
+        }
+    }
+    Ok(cpus)
+}
+
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    cpus.iter().map(|cpu| cpu.to_string()).collect::<Vec<String>>().join(",")
+}
+
+/// Pins the calling thread, returns false when the OS refused
+pub(crate) fn pin_current_thread(cpu: usize) -> bool {
+    core_affinity::set_for_current(core_affinity::CoreId { id: cpu })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpus_and_ranges() {
+        assert_eq!(parse_cpu_list("0-3,8, 10-11").unwrap(), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(parse_cpu_list("5-5").unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn rejects_reversed_ranges() {
+        assert!(parse_cpu_list("3-0").unwrap_err().contains("reversed"));
+    }
+
+    #[test]
+    fn rejects_cpus_past_the_kernel_limit() {
+        assert!(parse_cpu_list("0-99999999999").is_err());
+        assert!(parse_cpu_list(&MAX_CPUS.to_string()).is_err());
+        assert_eq!(parse_cpu_list(&format!("{}", MAX_CPUS - 1)).unwrap(), vec![MAX_CPUS - 1]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::job::Job;
+use crate::session::{Component, StressSession};
+use crate::stressors::StressorRegistry;
+
+/// Every test of the suite runs this long, scores are only comparable with the same duration
+pub const BENCHMARK_TEST_DURATION: Duration = Duration::from_secs(10);
+
+/// The stressors that make up the suite. Changing this list changes what a score means, so new
+/// stressors are not added here automatically.
+pub const BENCHMARK_SUITE: [&str; 8] = [
+    "Fibonacci",
+    "Float Addition",
+    "Float Multiplication",
+    "Float Division",
+    "Square Root",
+    "Quake Inverse Square Root",
+    "Matrix Multiplication",
+    "Primes",
+];
+
+// a score of 1000 means one thread was as fast as a single core of the reference machine
+const REFERENCE_SCORE: f64 = 1000.0;
+const REFERENCE_JSON: &str = include_str!("benchmark_reference.json");
+
+/// Single-core throughput of every suite stressor on the reference machine
+#[derive(Serialize, Deserialize)]
+pub struct ReferenceBaseline {
+    pub machine: String,
+    /// Work per second of one thread, by stressor name
+    pub throughput: HashMap<String, f64>,
+}
+
+impl ReferenceBaseline {
+    /// The baseline that ships with ezstress
+    pub fn shipped() -> ReferenceBaseline {
+        serde_json::from_str(REFERENCE_JSON).expect("The shipped benchmark reference is valid JSON")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BenchmarkMode {
+    SingleThreaded,
+    AllThreads,
+}
+
+pub struct BenchmarkTest {
+    pub stressor: &'static str,
+    pub mode: BenchmarkMode,
+    pub threads: usize,
+    pub thermal_ceiling: f32,
+}
+
+impl BenchmarkTest {
+    pub fn session(&self, registry: &StressorRegistry) -> Result<StressSession, String> {
+        let stressor = registry.find(self.stressor)
+            .ok_or_else(|| format!("The benchmark needs the {} stressor", self.stressor))?;
+
+        StressSession::builder()
+            .component(Component::Cpu)
+            .stressor(stressor)
+            .threads(self.threads)
+            .duration(BENCHMARK_TEST_DURATION)
+            .thermal_ceiling(self.thermal_ceiling)
+            .build()
+    }
+}
+
+/// Every suite stressor single-threaded first, then on `threads` threads
+pub fn benchmark_tests(threads: usize, thermal_ceiling: f32) -> Vec<BenchmarkTest> {
+    let single = BENCHMARK_SUITE.iter().map(|stressor| BenchmarkTest { stressor, mode: BenchmarkMode::SingleThreaded, threads: 1, thermal_ceiling });
+    let all = BENCHMARK_SUITE.iter().map(|stressor| BenchmarkTest { stressor, mode: BenchmarkMode::AllThreads, threads, thermal_ceiling });
+    single.chain(all).collect()
+}
+
+pub struct BenchmarkResult {
+    pub stressor: String,
+    pub mode: BenchmarkMode,
+    pub threads: usize,
+    pub throughput: f64,
+    pub score: f64,
+}
+
+pub struct BenchmarkReport {
+    pub results: Vec<BenchmarkResult>,
+    /// Geometric mean of the single-threaded scores
+    pub single_thread_score: f64,
+    /// Geometric mean of the all-threads scores
+    pub multi_thread_score: f64,
+}
+
+impl BenchmarkReport {
+    pub fn new(tests: &[BenchmarkTest], jobs: &[Job], reference: &ReferenceBaseline) -> Result<BenchmarkReport, String> {
+        let results = tests.iter().zip(jobs).map(|(test, job)| {
+            let reference_throughput = reference.throughput.get(test.stressor)
+                .ok_or_else(|| format!("The benchmark reference has no result for {}", test.stressor))?;
+            Ok(BenchmarkResult {
+                stressor: test.stressor.to_string(),
+                mode: test.mode,
+                threads: test.threads,
+                throughput: job.throughput(),
+                score: job.throughput() / reference_throughput * REFERENCE_SCORE,
+            })
+        }).collect::<Result<Vec<_>, String>>()?;
+
+        let geometric_mean = |mode| {
+            let scores: Vec<f64> = results.iter().filter(|result| result.mode == mode).map(|result| result.score).collect();
+            match scores.len() {
+                0 => 0.0,
+                count => (scores.iter().map(|score| score.max(f64::MIN_POSITIVE).ln()).sum::<f64>() / count as f64).exp(),
+            }
+        };
+
+        Ok(BenchmarkReport {
+            single_thread_score: geometric_mean(BenchmarkMode::SingleThreaded),
+            multi_thread_score: geometric_mean(BenchmarkMode::AllThreads),
+            results,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control::StopReason;
+    use crate::watchdog::DEFAULT_THERMAL_CEILING;
+    use super::*;
+
+    fn job(throughput: f64) -> Job {
+        let work_done = (throughput * BENCHMARK_TEST_DURATION.as_secs_f64()) as u64;
+        Job {
+            name: String::new(),
+            total_iterations: work_done,
+            work_done,
+            unit: String::from("iterations"),
+            errors_detected: None,
+            cpu_count: None,
+            core_mapping: vec![],
+            unpinned_cpus: vec![],
+            elapsed: BENCHMARK_TEST_DURATION,
+            average_cpu_temp: None,
+            min_cpu_temp: None,
+            max_cpu_temp: None,
+            average_power: None,
+            average_cpu_usage: None,
+            fan_stats: vec![],
+            warnings: vec![],
+            steady_state: None,
+            memory_stats: None,
+            details: vec![],
+            thermal_profile: None,
+            stop_reason: StopReason::TimeLimit,
+            thermal_trip: None,
+        }
+    }
+
+    fn reference(throughput: &[(&str, f64)]) -> ReferenceBaseline {
+        ReferenceBaseline {
+            machine: String::from("Test Machine"),
+            throughput: throughput.iter().map(|(stressor, throughput)| (stressor.to_string(), *throughput)).collect(),
+        }
+    }
+
+    fn test(stressor: &'static str, mode: BenchmarkMode, threads: usize) -> BenchmarkTest {
+        BenchmarkTest { stressor, mode, threads, thermal_ceiling: DEFAULT_THERMAL_CEILING }
+    }
+
+    #[test]
+    fn shipped_reference_covers_the_suite() {
+        let reference = ReferenceBaseline::shipped();
+        for stressor in BENCHMARK_SUITE {
+            assert!(reference.throughput.get(stressor).is_some_and(|throughput| *throughput > 0.0), "{stressor}");
+        }
+        let tests = benchmark_tests(4, 90.0);
+        assert_eq!(tests.len(), BENCHMARK_SUITE.len() * 2);
+        assert!(tests.iter().all(|test| test.thermal_ceiling == 90.0));
+    }
+
+    #[test]
+    fn scores_are_relative_to_the_reference() {
+        let reference = reference(&[("Primes", 100.0), ("Fibonacci", 50.0)]);
+        let tests = [test("Primes", BenchmarkMode::SingleThreaded, 1), test("Fibonacci", BenchmarkMode::SingleThreaded, 1)];
+        let report = BenchmarkReport::new(&tests, &[job(100.0), job(75.0)], &reference).unwrap();
+        assert_eq!(report.results[0].score, 1000.0);
+        assert_eq!(report.results[1].score, 1500.0);
+        assert_eq!(report.results[1].throughput, 75.0);
+    }
+
+    #[test]
+    fn suite_scores_are_the_geometric_mean() {
+        let reference = reference(&[("Primes", 100.0), ("Fibonacci", 100.0)]);
+        let tests = [
+            test("Primes", BenchmarkMode::SingleThreaded, 1),
+            test("Fibonacci", BenchmarkMode::SingleThreaded, 1),
+            test("Primes", BenchmarkMode::AllThreads, 8),
+            test("Fibonacci", BenchmarkMode::AllThreads, 8),
+        ];
+        let report = BenchmarkReport::new(&tests, &[job(50.0), job(200.0), job(800.0), job(800.0)], &reference).unwrap();
+        // 500 and 2000 average to 1000, an arithmetic mean would give 1250
+        assert!((report.single_thread_score - 1000.0).abs() < 1e-9);
+        assert!((report.multi_thread_score - 8000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_reference_is_an_error() {
+        let reference = reference(&[("Primes", 100.0)]);
+        let tests = [test("Fibonacci", BenchmarkMode::SingleThreaded, 1)];
+        assert!(BenchmarkReport::new(&tests, &[job(100.0)], &reference).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use ezstress::compare::{compare_configs, compare_jobs, Better, RegressionThresholds};
+use ezstress::history::{RunHistory, StoredRun};
+use ezstress::job::pretty_print_int;
+
+// `history show` prints at most this many rows of a run's time series
+const MAX_SERIES_ROWS: usize = 20;
+pub const REGRESSION_EXIT_CODE: i32 = 4;
+
+#[derive(Subcommand)]
+pub enum HistoryCommand {
+    /// List every recorded run, newest first
+    List,
+    /// Show the configuration, system and results of a run
+    Show { id: i64 },
+    /// Remove a run from the history
+    Delete { id: i64 },
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// ID of the baseline run, see `history list`
+    baseline: i64,
+    /// ID of the run to check against the baseline
+    candidate: i64,
+    /// Fail when throughput dropped by more than this many percent
+    #[arg(long, default_value_t = RegressionThresholds::default().max_throughput_drop)]
+    max_throughput_drop: f64,
+    /// Fail when a temperature rose by more than this many degrees Celsius
+    #[arg(long, default_value_t = RegressionThresholds::default().max_temperature_rise)]
+    max_temperature_rise: f32,
+    /// Fail when package power rose by more than this many percent
+    #[arg(long, default_value_t = RegressionThresholds::default().max_power_rise)]
+    max_power_rise: f64,
+    /// Fail when the candidate detected more than this many additional errors
+    #[arg(long, default_value_t = RegressionThresholds::default().max_new_errors)]
+    max_new_errors: u64,
+}
+
+/// Prints the comparison and returns whether the candidate passed
+pub fn run_compare_command(args: CompareArgs, history: &RunHistory) -> Result<bool, String> {
+    let load = |id| history.get(id)?.ok_or_else(|| format!("There is no run with id {id}"));
+    let baseline = load(args.baseline)?;
+    let candidate = load(args.candidate)?;
+    let thresholds = RegressionThresholds {
+        max_throughput_drop: args.max_throughput_drop,
+        max_temperature_rise: args.max_temperature_rise,
+        max_power_rise: args.max_power_rise,
+        max_new_errors: args.max_new_errors,
+    };
+    let config_warnings = compare_configs(&baseline.config, &candidate.config)?;
+    let mut comparison = compare_jobs(&baseline.job, &candidate.job, &thresholds)?;
+    comparison.warnings.extend(config_warnings);
+
+    println!("{} run #{} ({}) against run #{} ({})\n", baseline.job.name.bold(),
+             candidate.id, candidate.recorded_at, baseline.id, baseline.recorded_at);
+    for warning in &comparison.warnings {
+        println!("{}", format!("⚠ {warning}").yellow());
+    }
+
+    let run_a = format!("Run #{}", baseline.id);
+    let run_b = format!("Run #{}", candidate.id);
+    println!("{:<26}  {:>18}  {:>18}  {:>9}", "Metric", run_a, run_b, "Delta");
+    for metric in &comparison.metrics {
+        let format_value = |value: Option<f64>| match value {
+            Some(value) if metric.unit.is_empty() => pretty_print_int(value as u64),
+            Some(value) if metric.better == Better::Higher => format!("{} {}", pretty_print_int(value as u64), metric.unit),
+            Some(value) => format!("{value:.1} {}", metric.unit),
+            None => String::from("-"),
+        };
+        let delta = metric.delta_percent().map_or_else(|| String::from("-"), |delta| format!("{delta:+.1}%"));
+        let delta = match (metric.regressed, metric.improved()) {
+            (true, _) => delta.red(),
+            (false, true) => delta.green(),
+            (false, false) => delta.yellow(),
+        };
+        println!("{:<26}  {:>18}  {:>18}  {:>9}", metric.name, format_value(metric.baseline), format_value(metric.candidate), delta);
+    }
+
+    if comparison.passed() {
+        println!("\n{}", "PASS".green().bold());
+    } else {
+        let failed = comparison.regressions().map(|metric| metric.name).collect::<Vec<&str>>().join(", ");
+        println!("\n{} regressed: {failed}", "FAIL".red().bold());
+    }
+    Ok(comparison.passed())
+}
+
+pub fn run_history_command(command: HistoryCommand, history: &RunHistory) -> Result<(), String> {
+    match command {
+        HistoryCommand::List => {
+            let runs = history.list()?;
+            if runs.is_empty() {
+                println!("No runs recorded yet");
+                return Ok(())
+            }
+
+            println!("{:>5}  {:<19}  {:<28}  {:<24}  {:>10}  {:>16}  {:>8}  {:>6}  Stop Reason",
+                     "ID", "Recorded", "Stressor", "Component", "Duration", "Throughput/s", "Max Temp", "Errors");
+            for run in runs {
+                let max_temp = run.max_cpu_temp.map_or_else(|| String::from("-"), |temp| format!("{temp:.1}°C"));
+                let errors = run.errors_detected.map_or_else(|| String::from("-"), |errors| errors.to_string());
+                println!("{:>5}  {:<19}  {:<28.28}  {:<24.24}  {:>10}  {:>16}  {:>8}  {:>6}  {}",
+                         run.id, run.recorded_at, run.stressor, run.component, format_duration(run.elapsed),
+                         pretty_print_int(run.throughput() as u64), max_temp, errors, run.stop_reason);
+            }
+            Ok(())
+        }
+        HistoryCommand::Show { id } => match history.get(id)? {
+            Some(run) => {
+                print_run(&run);
+                Ok(())
+            }
+            None => Err(format!("There is no run with id {id}")),
+        },
+        HistoryCommand::Delete { id } => match history.delete(id)? {
+            true => {
+                println!("Deleted run #{id}");
+                Ok(())
+            }
+            false => Err(format!("There is no run with id {id}")),
+        },
+    }
+}
+
+fn print_run(run: &StoredRun) {
+    let config = &run.config;
+    let conditions = &config.conditions;
+
+    println!("{} recorded {}\n", format!("Run #{}", run.id).bold(), run.recorded_at);
+    println!("{}", run.system);
+
+    println!("Configuration");
+    println!("⇁ Component: {}", config.component);
+    println!("⇁ Stressor: {}", config.stressor);
+    if let Some(threads) = config.threads {
+        println!("⇁ Threads: {threads}");
+    }
+    if let Some(pinning) = &config.pinning {
+        println!("⇁ Thread Pinning: {pinning}");
+    }
+    if let Some(profile) = &config.load_profile {
+        println!("⇁ Load Profile: {profile}");
+    }
+    if let Some(duration) = conditions.duration {
+        println!("⇁ Time Limit: {}", format_duration(duration));
+    }
+    if let Some(temperature) = conditions.stop_temperature {
+        println!("⇁ Stop Temperature: {temperature}°C");
+    }
+    if let Some(stabilization) = conditions.stabilization {
+        println!("⇁ Thermal Stabilization: ±{}°C over {}", stabilization.tolerance, format_duration(stabilization.window));
+    }
+    if conditions.stop_on_fan_failure {
+        println!("⇁ Stop On Fan Failure: yes");
+    }
+    if let Some(memory) = conditions.min_available_memory {
+        println!("⇁ Minimum Available Memory: {} MB", memory / 1024 / 1024);
+    }
+    if let Some(baseline) = config.baseline {
+        println!("⇁ Idle Baseline: {}", format_duration(baseline.idle_duration));
+    }
+    println!("⇁ Thermal Ceiling: {}°C", config.thermal_ceiling);
+    println!("⇁ Verification: {}", if config.verify { "on" } else { "off" });
+
+    println!("{}", run.job);
+    println!("⇁ Duration: {}", format_duration(run.job.elapsed));
+
+    if run.samples.is_empty() {
+        return
+    }
+
+    println!("\nTime Series");
+    println!("{:>10}  {:>10}  {:>16}", "Elapsed", "CPU Temp", "Throughput/s");
+    let step = run.samples.len().div_ceil(MAX_SERIES_ROWS);
+    for sample in run.samples.iter().step_by(step) {
+        let temp = sample.cpu_temp.map_or_else(|| String::from("-"), |temp| format!("{temp:.1}°C"));
+        println!("{:>10}  {:>10}  {:>16}", format_duration(sample.elapsed), temp, pretty_print_int(sample.throughput as u64));
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        seconds if seconds >= 3600 => format!("{}h {}m {}s", seconds / 3600, (seconds % 3600) / 60, seconds % 60),
+        seconds if seconds >= 60 => format!("{}m {}s", seconds / 60, seconds % 60),
+        seconds => format!("{seconds}s"),
+    }
+}
+
+/// Where the CLI keeps its run history, `--history-db` overrides the default location
+pub fn open_history(path: Option<&std::path::Path>) -> Result<RunHistory, String> {
+    match path {
+        Some(path) => RunHistory::open(path),
+        None => RunHistory::open_default(),
+    }
+}
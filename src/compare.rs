@@ -0,0 +1,326 @@
+use std::fmt::Display;
+use crate::job::Job;
+use crate::session::RunConfig;
+
+// how much longer or shorter one run may have been before the comparison gets a warning, in
+// percent of the baseline
+const MAX_DURATION_MISMATCH: f64 = 10.0;
+
+/// How much worse a candidate run may be than its baseline before it counts as a regression
+#[derive(Clone, Copy)]
+pub struct RegressionThresholds {
+    /// In percent of the baseline throughput
+    pub max_throughput_drop: f64,
+    /// In degrees Celsius, applies to the average, peak and steady-state temperatures
+    pub max_temperature_rise: f32,
+    /// In percent of the baseline package power
+    pub max_power_rise: f64,
+    /// Additional verification errors allowed over the baseline
+    pub max_new_errors: u64,
+}
+
+impl Default for RegressionThresholds {
+    fn default() -> Self {
+        RegressionThresholds {
+            max_throughput_drop: 5.0,
+            max_temperature_rise: 3.0,
+            max_power_rise: 10.0,
+            max_new_errors: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Better {
+    Higher,
+    Lower,
+}
+
+pub struct MetricDelta {
+    pub name: &'static str,
+    pub unit: String,
+    pub better: Better,
+    pub baseline: Option<f64>,
+    pub candidate: Option<f64>,
+    pub regressed: bool,
+}
+
+impl MetricDelta {
+    /// Change from baseline to candidate in percent of the baseline
+    pub fn delta_percent(&self) -> Option<f64> {
+        match (self.baseline, self.candidate) {
+            (Some(baseline), Some(candidate)) if baseline != 0.0 => Some((candidate - baseline) / baseline.abs() * 100.0),
+            _ => None,
+        }
+    }
+
+    /// True when the candidate moved the right way, or did not move at all
+    pub fn improved(&self) -> bool {
+        match (self.baseline, self.candidate, self.better) {
+            (Some(baseline), Some(candidate), Better::Higher) => candidate >= baseline,
+            (Some(baseline), Some(candidate), Better::Lower) => candidate <= baseline,
+            _ => true,
+        }
+    }
+}
+
+pub struct Comparison {
+    pub metrics: Vec<MetricDelta>,
+    /// Differences between the runs that make the metrics less comparable
+    pub warnings: Vec<String>,
+}
+
+impl Comparison {
+    pub fn passed(&self) -> bool {
+        !self.metrics.iter().any(|metric| metric.regressed)
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &MetricDelta> {
+        self.metrics.iter().filter(|metric| metric.regressed)
+    }
+}
+
+/// Checks that two runs were set up the same way. Differences in what the workers did, such as
+/// another load profile or disk block size, are errors because any change in throughput would
+/// come from the setup. Differences in where the workers ran are returned as warnings.
+pub fn compare_configs(baseline: &RunConfig, candidate: &RunConfig) -> Result<Vec<String>, String> {
+    fn describe<T: Display>(value: Option<&T>, none: &str) -> String {
+        value.map_or_else(|| none.to_string(), T::to_string)
+    }
+
+    if baseline.component != candidate.component {
+        return Err(format!("Only runs on the same component can be compared, got {} and {}", baseline.component, candidate.component));
+    }
+    // runs saved before the workload was recorded have none
+    if !baseline.workload.is_empty() && !candidate.workload.is_empty() && baseline.workload != candidate.workload {
+        return Err(format!("Only runs with the same workload can be compared, got \"{}\" and \"{}\"", baseline.workload, candidate.workload));
+    }
+    let (baseline_load, candidate_load) = (describe(baseline.load_profile.as_ref(), "Full load"), describe(candidate.load_profile.as_ref(), "Full load"));
+    if baseline_load != candidate_load {
+        return Err(format!("Only runs with the same load profile can be compared, got {baseline_load} and {candidate_load}"));
+    }
+    if baseline.verify != candidate.verify {
+        return Err(String::from("Only runs that both verified their results, or both did not, can be compared"));
+    }
+
+    let mut warnings = Vec::new();
+    let (baseline_pinning, candidate_pinning) = (describe(baseline.pinning.as_ref(), "Unpinned"), describe(candidate.pinning.as_ref(), "Unpinned"));
+    if baseline_pinning != candidate_pinning {
+        warnings.push(format!("The threads were placed differently ({baseline_pinning} and {candidate_pinning}), throughput and temperatures may differ because of it"));
+    }
+    Ok(warnings)
+}
+
+/// Diffs a candidate run against a baseline run of the same stressor on the same number of
+/// threads. Metrics one of the runs does not have, e.g. power without access to the energy
+/// counters, are listed but never fail. Runs of clearly different length get a warning, their
+/// temperatures are unlikely to compare.
+pub fn compare_jobs(baseline: &Job, candidate: &Job, thresholds: &RegressionThresholds) -> Result<Comparison, String> {
+    if baseline.name != candidate.name {
+        return Err(format!("Only runs of the same stressor can be compared, got {} and {}", baseline.name, candidate.name));
+    }
+    if baseline.cpu_count != candidate.cpu_count {
+        let threads = |job: &Job| job.cpu_count.map_or_else(|| String::from("a GPU"), |threads| format!("{threads} threads"));
+        return Err(format!("Only runs on the same number of threads can be compared, got {} and {}", threads(baseline), threads(candidate)));
+    }
+
+    let mut warnings = Vec::new();
+    let (baseline_time, candidate_time) = (baseline.elapsed.as_secs_f64(), candidate.elapsed.as_secs_f64());
+    if (candidate_time - baseline_time).abs() > baseline_time * MAX_DURATION_MISMATCH / 100.0 {
+        warnings.push(format!("The runs lasted {baseline_time:.0}s and {candidate_time:.0}s, temperatures and power of runs this different in length may not be comparable"));
+    }
+
+    let temperature = |name, baseline: Option<f32>, candidate: Option<f32>| {
+        let regressed = matches!((baseline, candidate), (Some(baseline), Some(candidate)) if candidate - baseline > thresholds.max_temperature_rise);
+        MetricDelta {
+            name,
+            unit: String::from("°C"),
+            better: Better::Lower,
+            baseline: baseline.map(f64::from),
+            candidate: candidate.map(f64::from),
+            regressed,
+        }
+    };
+
+    let mut metrics = Vec::new();
+
+    let throughput = MetricDelta {
+        name: "Throughput",
+        unit: format!("{}/s", baseline.unit),
+        better: Better::Higher,
+        baseline: Some(baseline.throughput()),
+        candidate: Some(candidate.throughput()),
+        regressed: false,
+    };
+    let throughput_drop = -throughput.delta_percent().unwrap_or(0.0);
+    metrics.push(MetricDelta { regressed: throughput_drop > thresholds.max_throughput_drop, ..throughput });
+
+    metrics.push(temperature("Average Temperature", baseline.average_cpu_temp, candidate.average_cpu_temp));
+    metrics.push(temperature("Peak Temperature", baseline.max_cpu_temp, candidate.max_cpu_temp));
+    metrics.push(temperature(
+        "Steady-State Temperature",
+        baseline.steady_state.as_ref().map(|steady| steady.temperature),
+        candidate.steady_state.as_ref().map(|steady| steady.temperature),
+    ));
+    metrics.push(temperature(
+        "Average Delta Over Idle",
+        baseline.thermal_profile.as_ref().and_then(|profile| profile.average_delta_over_idle),
+        candidate.thermal_profile.as_ref().and_then(|profile| profile.average_delta_over_idle),
+    ));
+
+    let power = MetricDelta {
+        name: "Package Power",
+        unit: String::from("W"),
+        better: Better::Lower,
+        baseline: baseline.average_power.map(f64::from),
+        candidate: candidate.average_power.map(f64::from),
+        regressed: false,
+    };
+    let power_rise = power.delta_percent().unwrap_or(0.0);
+    metrics.push(MetricDelta { regressed: power_rise > thresholds.max_power_rise, ..power });
+
+    let errors = MetricDelta {
+        name: "Errors Detected",
+        unit: String::new(),
+        better: Better::Lower,
+        baseline: baseline.errors_detected.map(|errors| errors as f64),
+        candidate: candidate.errors_detected.map(|errors| errors as f64),
+        regressed: false,
+    };
+    let new_errors = candidate.errors_detected.unwrap_or(0).saturating_sub(baseline.errors_detected.unwrap_or(0));
+    metrics.push(MetricDelta { regressed: candidate.errors_detected.is_some() && new_errors > thresholds.max_new_errors, ..errors });
+
+    // a metric neither run has only clutters the table
+    metrics.retain(|metric| metric.baseline.is_some() || metric.candidate.is_some());
+    Ok(Comparison { metrics, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::affinity::PinningStrategy;
+    use crate::control::StopReason;
+    use crate::load::LoadProfile;
+    use super::*;
+
+    fn job(work_done: u64, seconds: u64, threads: usize) -> Job {
+        Job {
+            name: String::from("Primes"),
+            total_iterations: work_done,
+            work_done,
+            unit: String::from("iterations"),
+            errors_detected: Some(0),
+            cpu_count: Some(threads),
+            core_mapping: vec![],
+            unpinned_cpus: vec![],
+            elapsed: Duration::from_secs(seconds),
+            average_cpu_temp: Some(70.0),
+            min_cpu_temp: None,
+            max_cpu_temp: None,
+            average_power: Some(100.0),
+            average_cpu_usage: None,
+            fan_stats: vec![],
+            warnings: vec![],
+            steady_state: None,
+            memory_stats: None,
+            details: vec![],
+            thermal_profile: None,
+            stop_reason: StopReason::TimeLimit,
+            thermal_trip: None,
+        }
+    }
+
+    fn config() -> RunConfig {
+        RunConfig {
+            component: String::from("CPU"),
+            stressor: String::from("Primes"),
+            workload: String::from("Trial division of every number below 100,000"),
+            threads: Some(4),
+            conditions: Default::default(),
+            baseline: None,
+            thermal_ceiling: 95.0,
+            verify: true,
+            pinning: None,
+            load_profile: None,
+        }
+    }
+
+    fn regressed(comparison: &Comparison, name: &str) -> bool {
+        comparison.metrics.iter().find(|metric| metric.name == name).unwrap().regressed
+    }
+
+    #[test]
+    fn throughput_drop_fails_only_past_the_threshold() {
+        let thresholds = RegressionThresholds::default();
+        let at_threshold = compare_jobs(&job(1000, 10, 4), &job(950, 10, 4), &thresholds).unwrap();
+        assert!(at_threshold.passed());
+        let past_threshold = compare_jobs(&job(1000, 10, 4), &job(949, 10, 4), &thresholds).unwrap();
+        assert!(regressed(&past_threshold, "Throughput"));
+        assert!(!past_threshold.passed());
+    }
+
+    #[test]
+    fn temperature_power_and_error_thresholds() {
+        let thresholds = RegressionThresholds::default();
+        let baseline = job(1000, 10, 4);
+
+        let mut warmer = job(1000, 10, 4);
+        warmer.average_cpu_temp = Some(73.0);
+        assert!(compare_jobs(&baseline, &warmer, &thresholds).unwrap().passed());
+        warmer.average_cpu_temp = Some(73.5);
+        assert!(regressed(&compare_jobs(&baseline, &warmer, &thresholds).unwrap(), "Average Temperature"));
+
+        let mut hungrier = job(1000, 10, 4);
+        hungrier.average_power = Some(110.0);
+        assert!(compare_jobs(&baseline, &hungrier, &thresholds).unwrap().passed());
+        hungrier.average_power = Some(111.0);
+        assert!(regressed(&compare_jobs(&baseline, &hungrier, &thresholds).unwrap(), "Package Power"));
+
+        let mut erroring = job(1000, 10, 4);
+        erroring.errors_detected = Some(1);
+        assert!(regressed(&compare_jobs(&baseline, &erroring, &thresholds).unwrap(), "Errors Detected"));
+        let lenient = RegressionThresholds { max_new_errors: 1, ..thresholds };
+        assert!(compare_jobs(&baseline, &erroring, &lenient).unwrap().passed());
+    }
+
+    #[test]
+    fn metrics_missing_from_one_run_never_fail() {
+        let mut candidate = job(1000, 10, 4);
+        candidate.average_power = None;
+        assert!(compare_jobs(&job(1000, 10, 4), &candidate, &RegressionThresholds::default()).unwrap().passed());
+    }
+
+    #[test]
+    fn different_thread_counts_are_refused() {
+        assert!(compare_jobs(&job(1000, 10, 4), &job(4000, 10, 16), &RegressionThresholds::default()).is_err());
+    }
+
+    #[test]
+    fn different_lengths_get_a_warning() {
+        let thresholds = RegressionThresholds::default();
+        assert!(compare_jobs(&job(1000, 60, 4), &job(1100, 66, 4), &thresholds).unwrap().warnings.is_empty());
+        assert_eq!(compare_jobs(&job(1000, 60, 4), &job(60000, 3600, 4), &thresholds).unwrap().warnings.len(), 1);
+    }
+
+    #[test]
+    fn runs_set_up_differently_are_refused() {
+        assert_eq!(compare_configs(&config(), &config()).unwrap(), Vec::<String>::new());
+
+        let profile = RunConfig { load_profile: Some(LoadProfile::Constant(50)), ..config() };
+        assert!(compare_configs(&config(), &profile).is_err());
+        let unverified = RunConfig { verify: false, ..config() };
+        assert!(compare_configs(&config(), &unverified).is_err());
+        let disk = |workload: &str| RunConfig { stressor: String::from("Disk I/O"), workload: workload.to_string(), ..config() };
+        assert!(compare_configs(&disk("random 4 KiB blocks"), &disk("random 64 KiB blocks")).is_err());
+        // runs saved before the workload was recorded
+        assert!(compare_configs(&disk(""), &disk("random 64 KiB blocks")).is_ok());
+    }
+
+    #[test]
+    fn runs_placed_differently_get_a_warning() {
+        let pinned = RunConfig { pinning: Some(PinningStrategy::SmtSiblings), ..config() };
+        let warnings = compare_configs(&config(), &pinned).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Unpinned and SMT siblings"), "{}", warnings[0]);
+    }
+}
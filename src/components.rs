@@ -2,6 +2,7 @@ use std::fmt::{Display, Formatter};
 use colored::Colorize;
 use ocl::{Device, DeviceType, Platform};
 use ocl::core::DeviceInfo;
+use serde::{Deserialize, Serialize};
 use sysinfo::{CpuExt, System, SystemExt};
 
 pub fn get_system_gpus(platform: &Option<Platform>) -> Option<Vec<Device>> {
@@ -22,6 +23,7 @@ pub fn get_system_gpus(platform: &Option<Platform>) -> Option<Vec<Device>> {
 
 
 
+#[derive(Serialize, Deserialize)]
 pub struct GreetingValues {
     pub host_name: String,
     pub os: String,
@@ -31,6 +33,7 @@ pub struct GreetingValues {
 
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct CPUInformation {
     pub name: String,
     // if intel hyper-threading or AMD SMT enabled on chip logical cores != physical cores
@@ -40,6 +43,7 @@ pub struct CPUInformation {
 
 
 
+#[derive(Serialize, Deserialize)]
 pub struct GPUInformation {
     pub name: String,
     pub mem: Option<usize>,
@@ -0,0 +1,163 @@
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    TimeLimit,
+    Temperature,
+    Cancelled,
+    ThermalCutoff,
+    FanFailure,
+    SteadyState,
+    LowMemory,
+    StressorFailed,
+}
+
+impl StopReason {
+    pub(crate) const ALL: [StopReason; 8] = [
+        StopReason::TimeLimit,
+        StopReason::Temperature,
+        StopReason::Cancelled,
+        StopReason::ThermalCutoff,
+        StopReason::FanFailure,
+        StopReason::SteadyState,
+        StopReason::LowMemory,
+        StopReason::StressorFailed,
+    ];
+
+    // 0 is reserved for "still running"
+    fn code(self) -> u8 {
+        Self::ALL.iter().position(|reason| *reason == self).unwrap() as u8 + 1
+    }
+
+    fn from_code(code: u8) -> Option<StopReason> {
+        Self::ALL.get((code as usize).checked_sub(1)?).copied()
+    }
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::TimeLimit => f.write_str("Time Limit exceeded"),
+            StopReason::Temperature => f.write_str("Temperature exceeded"),
+            StopReason::Cancelled => f.write_str("Cancelled"),
+            StopReason::ThermalCutoff => f.write_str("Thermal safety cutoff"),
+            StopReason::FanFailure => f.write_str("Fan failure detected"),
+            StopReason::SteadyState => f.write_str("Thermals stabilized"),
+            StopReason::LowMemory => f.write_str("Available memory below threshold"),
+            StopReason::StressorFailed => f.write_str("Stressor failed to start"),
+        }
+    }
+}
+
+/// Shared stop and pause control of a run. Clones all control the same run, so it can be
+/// handed to a signal handler, another thread or a remote command while the run is going.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<ControlState>,
+}
+
+#[derive(Default)]
+struct ControlState {
+    stop_reason: AtomicU8,
+    /// Kept apart from the stop reason, a cancel after the load has stopped still ends the cooldown
+    cancelled: AtomicBool,
+    paused: AtomicBool,
+    pause_state: Mutex<PauseState>,
+    resumed: Condvar,
+}
+
+#[derive(Default)]
+struct PauseState {
+    paused_at: Option<Instant>,
+    paused_total: Duration,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Stops the run with [`StopReason::Cancelled`]
+    pub fn cancel(&self) -> bool {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.stop(StopReason::Cancelled)
+    }
+
+    /// Whether [`CancellationToken::cancel`] was called, even if the run had already stopped for another reason
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Stops the run, returns false if it was already stopped. The first reason always wins.
+    pub fn stop(&self, reason: StopReason) -> bool {
+        let stopped = self.inner.stop_reason
+            .compare_exchange(0, reason.code(), Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok();
+        // paused workers have to wake up to notice they are done
+        let _state = self.inner.pause_state.lock().unwrap();
+        self.inner.resumed.notify_all();
+        stopped
+    }
+
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        StopReason::from_code(self.inner.stop_reason.load(Ordering::SeqCst))
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.inner.stop_reason.load(Ordering::SeqCst) != 0
+    }
+
+    /// Parks every worker after its current iteration, the threads stay alive
+    pub fn pause(&self) {
+        let mut state = self.inner.pause_state.lock().unwrap();
+        if !self.inner.paused.swap(true, Ordering::SeqCst) {
+            state.paused_at = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.inner.pause_state.lock().unwrap();
+        if self.inner.paused.swap(false, Ordering::SeqCst) {
+            if let Some(paused_at) = state.paused_at.take() {
+                state.paused_total += paused_at.elapsed();
+            }
+        }
+        self.inner.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::SeqCst)
+    }
+
+    /// Blocks while the run is paused, returns immediately otherwise
+    pub fn wait_while_paused(&self) {
+        if !self.is_paused() {
+            return
+        }
+
+        let mut state = self.inner.pause_state.lock().unwrap();
+        while self.is_paused() && !self.is_stopped() {
+            state = self.inner.resumed.wait(state).unwrap();
+        }
+    }
+
+    /// Time since `start_time` without the time spent paused
+    pub fn active_time(&self, start_time: Instant) -> Duration {
+        let state = self.inner.pause_state.lock().unwrap();
+        let current_pause = state.paused_at.map_or(Duration::ZERO, |paused_at| paused_at.elapsed());
+        start_time.elapsed().saturating_sub(state.paused_total + current_pause)
+    }
+
+    /// Clears the stop reason and pause state so the token can control another run
+    pub(crate) fn reset(&self) {
+        let mut state = self.inner.pause_state.lock().unwrap();
+        self.inner.stop_reason.store(0, Ordering::SeqCst);
+        self.inner.cancelled.store(false, Ordering::SeqCst);
+        self.inner.paused.store(false, Ordering::SeqCst);
+        *state = PauseState::default();
+    }
+}
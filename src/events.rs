@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+use serde::Serialize;
+use crate::job::Job;
+use crate::sensors::{CoreLoad, SensorReading};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Stage {
+    IdleBaseline,
+    /// The workers get ready before the load starts, e.g. by filling their test files
+    Prepare,
+    Load,
+    Cooldown,
+}
+
+#[derive(Clone)]
+pub enum Event {
+    Started {
+        stressor: String,
+        /// What the work in [`Sample::work_done`] is measured in
+        unit: String,
+        /// None for GPU runs
+        threads: Option<usize>,
+    },
+    Sample(Sample),
+    Warning(String),
+    Error(String),
+    StageChanged(Stage),
+    Finished(Arc<Job>),
+}
+
+#[derive(Clone, Serialize)]
+pub struct Sample {
+    pub elapsed: Duration,
+    /// None when the run is not time limited
+    pub remaining: Option<Duration>,
+    pub cpu_temp: Option<f32>,
+    pub temperatures: Vec<SensorReading>,
+    pub cores: Vec<CoreLoad>,
+    /// Total work done so far, in the stressor's unit
+    pub work_done: u64,
+    /// One entry per worker thread, GPU runs report their command queue as a single worker
+    pub workers: Vec<WorkerProgress>,
+    /// Work per second since the previous sample
+    pub throughput: f64,
+}
+
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct WorkerProgress {
+    pub iterations: u64,
+    pub work_done: u64,
+    /// Verification failures, always 0 when verification is off
+    pub errors: u64,
+}
+
+/// Fans every emitted [`Event`] out to all subscribers. Subscribers that hung up are dropped
+/// on the next emit, so a slow or gone front-end never blocks a run.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<Event>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub fn emit(&self, event: Event) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use crate::components::GreetingValues;
+use crate::events::{Event, Stage};
+use crate::job::Job;
+use crate::session::RunConfig;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at INTEGER NOT NULL,
+    stressor TEXT NOT NULL,
+    component TEXT NOT NULL,
+    elapsed REAL NOT NULL,
+    work_done INTEGER NOT NULL,
+    unit TEXT NOT NULL,
+    max_cpu_temp REAL,
+    errors_detected INTEGER,
+    stop_reason TEXT NOT NULL,
+    config TEXT NOT NULL,
+    system TEXT NOT NULL,
+    job TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS samples (
+    run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    elapsed REAL NOT NULL,
+    cpu_temp REAL,
+    work_done INTEGER NOT NULL,
+    throughput REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS samples_by_run ON samples(run_id);
+";
+
+/// One point of a run's time series, only the load stage is recorded
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct HistorySample {
+    pub elapsed: Duration,
+    pub cpu_temp: Option<f32>,
+    pub work_done: u64,
+    pub throughput: f64,
+}
+
+/// The columns `history list` shows, without decoding the stored JSON
+pub struct RunSummary {
+    pub id: i64,
+    /// Local time, formatted by SQLite
+    pub recorded_at: String,
+    pub stressor: String,
+    pub component: String,
+    pub elapsed: Duration,
+    pub work_done: u64,
+    pub unit: String,
+    pub max_cpu_temp: Option<f32>,
+    pub errors_detected: Option<u64>,
+    pub stop_reason: String,
+}
+
+impl RunSummary {
+    /// Average work per second over the whole run
+    pub fn throughput(&self) -> f64 {
+        self.work_done as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+pub struct StoredRun {
+    pub id: i64,
+    pub recorded_at: String,
+    pub config: RunConfig,
+    pub system: GreetingValues,
+    pub job: Job,
+    /// Empty unless the run was recorded with its time series
+    pub samples: Vec<HistorySample>,
+}
+
+/// Every finished run, kept in a SQLite database so machines can be compared over time
+pub struct RunHistory {
+    connection: Connection,
+}
+
+impl RunHistory {
+    /// `history.db` in the user's data directory, e.g. `~/.local/share/ezstress` on Linux
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("ezstress").join("history.db"))
+    }
+
+    pub fn open_default() -> Result<RunHistory, String> {
+        let path = RunHistory::default_path().ok_or("Could not find a data directory for the run history")?;
+        RunHistory::open(&path)
+    }
+
+    pub fn open(path: &Path) -> Result<RunHistory, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Could not create {}: {e}", parent.display()))?;
+        }
+
+        let connection = Connection::open(path)
+            .map_err(|e| format!("Could not open the run history at {}: {e}", path.display()))?;
+        connection.execute_batch("PRAGMA foreign_keys = ON;")
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .map_err(|e| format!("Could not set up the run history: {e}"))?;
+
+        Ok(RunHistory { connection })
+    }
+
+    /// Stores a finished run and returns its id
+    pub fn record(
+        &mut self,
+        config: &RunConfig,
+        system: &GreetingValues,
+        job: &Job,
+        samples: &[HistorySample],
+    ) -> Result<i64, String> {
+        let config_json = serde_json::to_string(config).map_err(|e| e.to_string())?;
+        let system_json = serde_json::to_string(system).map_err(|e| e.to_string())?;
+        let job_json = serde_json::to_string(job).map_err(|e| e.to_string())?;
+
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        transaction.execute(
+            "INSERT INTO runs (recorded_at, stressor, component, elapsed, work_done, unit, max_cpu_temp, errors_detected, stop_reason, config, system, job)
+             VALUES (strftime('%s', 'now'), ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                job.name,
+                config.component,
+                job.elapsed.as_secs_f64(),
+                job.work_done as i64,
+                job.unit,
+                job.max_cpu_temp,
+                job.errors_detected.map(|errors| errors as i64),
+                job.stop_reason.to_string(),
+                config_json,
+                system_json,
+                job_json,
+            ],
+        ).map_err(|e| format!("Could not save the run: {e}"))?;
+        let id = transaction.last_insert_rowid();
+
+        {
+            let mut insert = transaction
+                .prepare("INSERT INTO samples (run_id, elapsed, cpu_temp, work_done, throughput) VALUES (?1, ?2, ?3, ?4, ?5)")
+                .map_err(|e| e.to_string())?;
+            for sample in samples {
+                insert.execute(params![id, sample.elapsed.as_secs_f64(), sample.cpu_temp, sample.work_done as i64, sample.throughput])
+                    .map_err(|e| format!("Could not save the run's samples: {e}"))?;
+            }
+        }
+
+        transaction.commit().map_err(|e| format!("Could not save the run: {e}"))?;
+        Ok(id)
+    }
+
+    /// Newest runs first
+    pub fn list(&self) -> Result<Vec<RunSummary>, String> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, datetime(recorded_at, 'unixepoch', 'localtime'), stressor, component, elapsed, work_done, unit, max_cpu_temp, errors_detected, stop_reason
+             FROM runs ORDER BY id DESC"
+        ).map_err(|e| e.to_string())?;
+
+        let rows = statement.query_map([], |row| Ok(RunSummary {
+            id: row.get(0)?,
+            recorded_at: row.get(1)?,
+            stressor: row.get(2)?,
+            component: row.get(3)?,
+            elapsed: Duration::from_secs_f64(row.get(4)?),
+            work_done: row.get::<_, i64>(5)? as u64,
+            unit: row.get(6)?,
+            max_cpu_temp: row.get(7)?,
+            errors_detected: row.get::<_, Option<i64>>(8)?.map(|errors| errors as u64),
+            stop_reason: row.get(9)?,
+        })).map_err(|e| e.to_string())?;
+
+        rows.collect::<Result<_, _>>().map_err(|e| format!("Could not read the run history: {e}"))
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<StoredRun>, String> {
+        let row = self.connection.query_row(
+            "SELECT datetime(recorded_at, 'unixepoch', 'localtime'), config, system, job FROM runs WHERE id = ?1",
+            [id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?)),
+        ).optional().map_err(|e| format!("Could not read run {id}: {e}"))?;
+
+        let Some((recorded_at, config, system, job)) = row else {
+            return Ok(None)
+        };
+
+        let mut statement = self.connection
+            .prepare("SELECT elapsed, cpu_temp, work_done, throughput FROM samples WHERE run_id = ?1 ORDER BY elapsed")
+            .map_err(|e| e.to_string())?;
+        let samples = statement.query_map([id], |row| Ok(HistorySample {
+            elapsed: Duration::from_secs_f64(row.get(0)?),
+            cpu_temp: row.get(1)?,
+            work_done: row.get::<_, i64>(2)? as u64,
+            throughput: row.get(3)?,
+        })).and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Could not read the samples of run {id}: {e}"))?;
+
+        let corrupt = |e: serde_json::Error| format!("Run {id} could not be read, it may be from an incompatible version: {e}");
+        Ok(Some(StoredRun {
+            id,
+            recorded_at,
+            config: serde_json::from_str(&config).map_err(corrupt)?,
+            system: serde_json::from_str(&system).map_err(corrupt)?,
+            job: serde_json::from_str(&job).map_err(corrupt)?,
+            samples,
+        }))
+    }
+
+    /// Returns false when there was no run with that id
+    pub fn delete(&self, id: i64) -> Result<bool, String> {
+        self.connection.execute("DELETE FROM runs WHERE id = ?1", [id])
+            .map(|deleted| deleted > 0)
+            .map_err(|e| format!("Could not delete run {id}: {e}"))
+    }
+}
+
+/// Collects the load samples of a run so they can be stored with [`RunHistory::record`]
+pub fn record_samples(events: Receiver<Event>) -> JoinHandle<Vec<HistorySample>> {
+    thread::spawn(move || {
+        let mut stage = None;
+        let mut samples = Vec::new();
+        for event in events {
+            match event {
+                Event::StageChanged(changed) => stage = Some(changed),
+                Event::Sample(sample) if stage == Some(Stage::Load) => samples.push(HistorySample {
+                    elapsed: sample.elapsed,
+                    cpu_temp: sample.cpu_temp,
+                    work_done: sample.work_done,
+                    throughput: sample.throughput,
+                }),
+                Event::Finished(_) => break,
+                _ => {}
+            }
+        }
+        samples
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::CPUInformation;
+    use crate::control::StopReason;
+    use super::*;
+
+    fn config() -> RunConfig {
+        RunConfig {
+            component: String::from("CPU"),
+            stressor: String::from("Primes"),
+            workload: String::from("Trial division of every number below 100,000"),
+            threads: Some(2),
+            conditions: Default::default(),
+            baseline: None,
+            thermal_ceiling: 95.0,
+            verify: true,
+            pinning: None,
+            load_profile: None,
+        }
+    }
+
+    fn system() -> GreetingValues {
+        GreetingValues {
+            host_name: String::from("rack-07"),
+            os: String::from("Linux"),
+            memory: 16 << 30,
+            cpu_information: CPUInformation { name: String::from("Test CPU"), logical_cores: 4, physical_cores: 2 },
+            gpu_information: vec![],
+        }
+    }
+
+    fn job() -> Job {
+        Job {
+            name: String::from("Primes"),
+            total_iterations: 1200,
+            work_done: 1200,
+            unit: String::from("iterations"),
+            errors_detected: Some(0),
+            cpu_count: Some(2),
+            core_mapping: vec![0, 2],
+            unpinned_cpus: vec![],
+            elapsed: Duration::from_secs(60),
+            average_cpu_temp: Some(71.5),
+            min_cpu_temp: Some(45.0),
+            max_cpu_temp: Some(82.0),
+            average_power: None,
+            average_cpu_usage: Some(50.0),
+            fan_stats: vec![],
+            warnings: vec![String::from("Something to look at")],
+            steady_state: None,
+            memory_stats: None,
+            details: vec![(String::from("Label"), String::from("Value"))],
+            thermal_profile: None,
+            stop_reason: StopReason::TimeLimit,
+            thermal_trip: None,
+        }
+    }
+
+    #[test]
+    fn runs_round_trip_through_the_database() {
+        let mut history = RunHistory::open(Path::new(":memory:")).unwrap();
+        let samples = [
+            HistorySample { elapsed: Duration::from_secs(1), cpu_temp: Some(60.0), work_done: 20, throughput: 20.0 },
+            HistorySample { elapsed: Duration::from_secs(2), cpu_temp: None, work_done: 40, throughput: 20.0 },
+        ];
+        let id = history.record(&config(), &system(), &job(), &samples).unwrap();
+
+        let runs = history.list().unwrap();
+        assert_eq!(runs.len(), 1);
+        let summary = &runs[0];
+        assert_eq!((summary.id, summary.stressor.as_str(), summary.component.as_str()), (id, "Primes", "CPU"));
+        assert_eq!((summary.work_done, summary.elapsed, summary.max_cpu_temp), (1200, Duration::from_secs(60), Some(82.0)));
+        assert_eq!((summary.errors_detected, summary.stop_reason.as_str()), (Some(0), "Time Limit exceeded"));
+        assert_eq!(summary.throughput(), 20.0);
+
+        let stored = history.get(id).unwrap().unwrap();
+        assert_eq!(stored.config.workload, config().workload);
+        assert_eq!(stored.config.threads, Some(2));
+        assert_eq!(stored.system.host_name, "rack-07");
+        assert_eq!(stored.job.core_mapping, [0, 2]);
+        assert_eq!(stored.job.details, job().details);
+        assert_eq!(stored.job.stop_reason, StopReason::TimeLimit);
+        assert_eq!(stored.samples.len(), 2);
+        assert_eq!(stored.samples[1].cpu_temp, None);
+        assert_eq!(stored.samples[1].work_done, 40);
+
+        assert!(history.delete(id).unwrap());
+        assert!(history.get(id).unwrap().is_none());
+        assert!(!history.delete(id).unwrap());
+    }
+}
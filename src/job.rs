@@ -0,0 +1,185 @@
+use std::time::Duration;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use crate::control::StopReason;
+use crate::reporting::{FanStats, MemoryStats, SteadyState, ThermalProfile};
+use crate::watchdog::ThermalTrip;
+
+/// The outcome of a single stress run.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub name: String,
+    pub total_iterations: u64,
+    /// Sum of the work reported by every iteration, in `unit`
+    pub work_done: u64,
+    pub unit: String,
+    /// None when verification was off
+    pub errors_detected: Option<u64>,
+    pub cpu_count: Option<usize>,
+    /// The logical cpu of every thread, empty when the threads were not pinned
+    #[serde(default)]
+    pub core_mapping: Vec<usize>,
+    /// Cpus of `core_mapping` the OS did not let a thread pin itself to, those threads ran unpinned
+    #[serde(default)]
+    pub unpinned_cpus: Vec<usize>,
+    /// How long the workers ran, without the time spent paused
+    pub elapsed: Duration,
+    pub average_cpu_temp: Option<f32>,
+    pub min_cpu_temp: Option<f32>,
+    pub max_cpu_temp: Option<f32>,
+    /// CPU package power in watts while under load, None without access to the energy counters
+    pub average_power: Option<f32>,
+    /// Average usage over every logical cpu while under load, in percent
+    pub average_cpu_usage: Option<f32>,
+    pub fan_stats: Vec<FanStats>,
+    pub warnings: Vec<String>,
+    pub steady_state: Option<SteadyState>,
+    pub memory_stats: Option<MemoryStats>,
+    /// Results specific to the stressor as label and value pairs
+    #[serde(default)]
+    pub details: Vec<(String, String)>,
+    pub thermal_profile: Option<ThermalProfile>,
+    pub stop_reason: StopReason,
+    pub thermal_trip: Option<ThermalTrip>,
+}
+
+impl Job {
+    /// Average work per second over the whole run
+    pub fn throughput(&self) -> f64 {
+        self.work_done as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl std::fmt::Display for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\n{} Stress Test Results ", self.name)?;
+
+        write!(f, "\n⇁ Job Name: {} \n⇁ Total Iterations: {} \n⇁ Stop Reasoning: {}",
+               self.name, pretty_print_int(self.total_iterations), self.stop_reason)?;
+
+        if self.unit != "iterations" {
+            write!(f, "\n⇁ Total Work: {} {}", pretty_print_int(self.work_done), self.unit)?;
+        }
+        write!(f, "\n⇁ Throughput: {}", format_throughput(self.throughput(), &self.unit))?;
+
+        if let Some(errors) = self.errors_detected {
+            let errors_text = match errors {
+                0 => "0".green(),
+                errors => pretty_print_int(errors).red(),
+            };
+            write!(f, "\n⇁ Errors Detected: {errors_text}")?;
+        }
+
+        if let Some(cpus) = self.cpu_count {
+            write!(f, "\n⇁ CPU Count: {cpus}")?;
+        }
+
+        if !self.core_mapping.is_empty() {
+            let mapping = self.core_mapping.iter().enumerate()
+                .map(|(thread, cpu)| format!("{thread}→{cpu}"))
+                .collect::<Vec<String>>()
+                .join(", ");
+            write!(f, "\n⇁ Thread Pinning (thread→CPU): {mapping}")?;
+        }
+
+        if let Some(max_temp) = self.max_cpu_temp {
+            write!(f, "\n⇁ Peak CPU Temperature: {max_temp:.2}°C")?;
+        }
+
+        if let Some(min_temp) = self.min_cpu_temp {
+            write!(f, "\n⇁ Minimum CPU Temperature: {min_temp:.2}°C")?;
+        }
+
+        if let Some(average_temp) = self.average_cpu_temp {
+            write!(f, "\n⇁ Average CPU Temperature: {average_temp:.2}°C")?;
+        }
+
+        if let Some(usage) = self.average_cpu_usage {
+            write!(f, "\n⇁ Average CPU Usage: {usage:.1}%")?;
+        }
+
+        if let Some(power) = self.average_power {
+            write!(f, "\n⇁ Average CPU Package Power: {power:.1} W")?;
+        }
+
+        if let Some(steady_state) = &self.steady_state {
+            write!(f, "\n⇁ Steady State Temperature: {:.2}°C", steady_state.temperature)?;
+            write!(f, "\n⇁ Time To Steady State: {}s", steady_state.time_to_steady_state.as_secs())?;
+        }
+
+        if let Some(profile) = &self.thermal_profile {
+            write!(f, "\n⇁ Idle CPU Temperature: {:.2}°C", profile.idle_temp)?;
+            if let Some(delta) = profile.peak_delta_over_idle {
+                write!(f, "\n⇁ Peak Delta Over Idle: {delta:+.2}°C")?;
+            }
+            if let Some(delta) = profile.average_delta_over_idle {
+                write!(f, "\n⇁ Average Delta Over Idle: {delta:+.2}°C")?;
+            }
+            match profile.time_to_idle {
+                Some(time) => write!(f, "\n⇁ Time To Return Within {:.1}°C Of Idle: {}s", profile.cooldown_tolerance, time.as_secs())?,
+                None => write!(f, "\n⇁ Time To Return Within {:.1}°C Of Idle: {}", profile.cooldown_tolerance, "did not cool down".yellow())?,
+            }
+        }
+
+        if let Some(memory) = &self.memory_stats {
+            write!(f, "\n⇁ Peak Memory Usage: {} / {} MB", memory.peak_used_memory / 1024 / 1024, memory.total_memory / 1024 / 1024)?;
+            write!(f, "\n⇁ Minimum Available Memory: {} MB", memory.min_available_memory / 1024 / 1024)?;
+            if memory.total_swap > 0 {
+                write!(f, "\n⇁ Peak Swap Usage: {} / {} MB", memory.peak_used_swap / 1024 / 1024, memory.total_swap / 1024 / 1024)?;
+            }
+        }
+
+        for (label, value) in &self.details {
+            write!(f, "\n⇁ {label}: {value}")?;
+        }
+
+        for fan in &self.fan_stats {
+            write!(f, "\n⇁ Fan {}: {} / {:.0} / {} RPM (min/avg/max)", fan.label, fan.min_rpm, fan.average_rpm(), fan.max_rpm)?;
+        }
+
+        for warning in &self.warnings {
+            write!(f, "\n⚠ Warning: {}", warning.yellow())?;
+        }
+
+        if let Some(trip) = &self.thermal_trip {
+            write!(f, "\n⇁ Thermal Cutoff: {}", trip.to_string().red())?;
+        }
+
+
+        Ok(())
+    }
+}
+
+/// `throughput` per second in `unit`, byte counts are shown in MB/s
+pub fn format_throughput(throughput: f64, unit: &str) -> String {
+    match unit {
+        "bytes" => format!("{:.1} MB/s", throughput / 1e6),
+        "MB" => format!("{throughput:.1} MB/s"),
+        unit => format!("{} {unit}/s", pretty_print_int(throughput as u64)),
+    }
+}
+
+pub fn pretty_print_int(i: u64) -> String {
+    let mut s = String::new();
+    let i_str = i.to_string();
+    let a = i_str.chars().rev().enumerate();
+    for (idx, val) in a {
+        if idx != 0 && idx % 3 == 0 {
+            s.insert(0, ',');
+        }
+        s.insert(0, val);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throughput_is_shown_per_second() {
+        assert_eq!(format_throughput(1_234_567.0, "bytes"), "1.2 MB/s");
+        assert_eq!(format_throughput(812.45, "MB"), "812.5 MB/s");
+        assert_eq!(format_throughput(12_345.6, "iterations"), "12,345 iterations/s");
+    }
+}
@@ -0,0 +1,22 @@
+pub mod stressors;
+pub mod sensors;
+pub mod reporting;
+pub mod components;
+pub mod watchdog;
+pub mod job;
+pub mod events;
+pub mod control;
+pub mod session;
+pub mod web;
+pub mod metrics;
+pub mod history;
+pub mod compare;
+pub mod benchmark;
+pub mod sweep;
+pub mod load;
+pub mod scaling;
+pub mod affinity;
+
+pub use control::{CancellationToken, StopReason};
+pub use job::Job;
+pub use session::{Component, RunConfig, StressSession, StressSessionBuilder};
@@ -0,0 +1,247 @@
+use std::f32::consts::TAU;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::control::CancellationToken;
+
+/// Busy time a worker collects before it sleeps off the idle share, short enough that the
+/// load looks constant to the OS and the fans
+pub const DUTY_CYCLE_PERIOD: Duration = Duration::from_millis(100);
+
+// how often the scheduler moves the target level along a profile
+const SCHEDULER_INTERVAL: Duration = Duration::from_millis(10);
+
+// sleeps are split up so a stopped run does not wait for a long idle share to end
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+/// Throttles a worker to a share of its core by sleeping between iterations
+#[derive(Default)]
+pub(crate) struct DutyCycle {
+    busy: Duration,
+}
+
+impl DutyCycle {
+    /// Call after every iteration with how long it took. `load` is in percent, once a period's
+    /// worth of busy time has been collected the matching idle time is slept off in one go.
+    pub(crate) fn throttle(&mut self, step_time: Duration, load: f32, token: &CancellationToken) {
+        if load >= 100.0 {
+            self.busy = Duration::ZERO;
+            return
+        }
+
+        self.busy += step_time;
+        let load = load.max(0.0) / 100.0;
+        if self.busy < DUTY_CYCLE_PERIOD.mul_f32(load) {
+            return
+        }
+
+        let idle = match load {
+            0.0 => DUTY_CYCLE_PERIOD,
+            load => self.busy.mul_f32((1.0 - load) / load),
+        };
+        self.busy = Duration::ZERO;
+
+        let mut slept = Duration::ZERO;
+        while slept < idle && !token.is_stopped() {
+            let slice = SLEEP_SLICE.min(idle - slept);
+            thread::sleep(slice);
+            slept += slice;
+        }
+    }
+
+    /// Idles briefly instead of running an iteration, for a target level of 0
+    pub(crate) fn rest(&mut self) {
+        self.busy = Duration::ZERO;
+        thread::sleep(SLEEP_SLICE);
+    }
+}
+
+/// How the target load of every worker changes over a run, levels are in percent of a core.
+/// Sudden steps in load are what expose an unstable VRM or power supply.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LoadProfile {
+    Constant(u8),
+    /// Linear from `from` to `to`, then holds `to`
+    Ramp { from: u8, to: u8, over: Duration },
+    /// Starts at `from` and adds `step` every `every` until it reaches `to`, then holds `to`
+    Steps { from: u8, to: u8, step: u8, every: Duration },
+    /// `high` for the first half of every period, `low` for the second, e.g. 0 and 100 for bursts
+    Square { low: u8, high: u8, period: Duration },
+    /// Starts at `low`, peaks at `high` halfway through every period
+    Sine { low: u8, high: u8, period: Duration },
+}
+
+impl LoadProfile {
+    /// The target load `elapsed` into the run, in percent
+    pub fn level_at(&self, elapsed: Duration) -> f32 {
+        let elapsed = elapsed.as_secs_f32();
+        match *self {
+            LoadProfile::Constant(level) => level as f32,
+            LoadProfile::Ramp { from, to, over } => {
+                let progress = (elapsed / over.as_secs_f32()).min(1.0);
+                from as f32 + (to as f32 - from as f32) * progress
+            }
+            LoadProfile::Steps { from, to, step, every } => {
+                let steps = (elapsed / every.as_secs_f32()).floor();
+                match to >= from {
+                    true => (from as f32 + steps * step as f32).min(to as f32),
+                    false => (from as f32 - steps * step as f32).max(to as f32),
+                }
+            }
+            LoadProfile::Square { low, high, period } => {
+                match (elapsed / period.as_secs_f32()).fract() < 0.5 {
+                    true => high as f32,
+                    false => low as f32,
+                }
+            }
+            LoadProfile::Sine { low, high, period } => {
+                let phase = (elapsed / period.as_secs_f32()).fract() * TAU;
+                low as f32 + (high as f32 - low as f32) * (1.0 - phase.cos()) / 2.0
+            }
+        }
+    }
+
+    /// The constant level, None for profiles that change over time
+    pub fn constant(&self) -> Option<u8> {
+        match self {
+            LoadProfile::Constant(level) => Some(*level),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let levels = match *self {
+            LoadProfile::Constant(0) => return Err("A constant load has to be at least 1 percent".to_string()),
+            LoadProfile::Constant(level) => vec![level],
+            LoadProfile::Ramp { from, to, .. } => vec![from, to],
+            LoadProfile::Steps { from, to, step: 0, .. } if from != to => {
+                return Err("The load steps have to be at least 1 percent".to_string())
+            }
+            LoadProfile::Steps { from, to, .. } => vec![from, to],
+            LoadProfile::Square { low, high, .. } | LoadProfile::Sine { low, high, .. } => vec![low, high],
+        };
+        if levels.iter().any(|level| *level > 100) {
+            return Err("Load levels have to be between 0 and 100 percent".to_string())
+        }
+
+        let shortest = match *self {
+            LoadProfile::Constant(_) => None,
+            LoadProfile::Ramp { over, .. } => Some(over),
+            LoadProfile::Steps { every, .. } => Some(every),
+            // every half of the wave needs a few duty cycles to show
+            LoadProfile::Square { period, .. } | LoadProfile::Sine { period, .. } => Some(period / 4),
+        };
+        match shortest {
+            Some(shortest) if shortest < DUTY_CYCLE_PERIOD => {
+                Err(format!("{self} changes faster than the {}ms duty cycle can follow", DUTY_CYCLE_PERIOD.as_millis()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for LoadProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadProfile::Constant(level) => write!(f, "Constant {level}%"),
+            LoadProfile::Ramp { from, to, over } => write!(f, "Ramp {from}% → {to}% over {}s", over.as_secs()),
+            LoadProfile::Steps { from, to, step, every } => write!(f, "Steps {from}% → {to}% by {step}% every {}s", every.as_secs()),
+            LoadProfile::Square { low, high, period } => write!(f, "Square wave {low}%/{high}% every {:.1}s", period.as_secs_f32()),
+            LoadProfile::Sine { low, high, period } => write!(f, "Sine wave {low}% to {high}% every {:.1}s", period.as_secs_f32()),
+        }
+    }
+}
+
+/// The target level the workers read, shared as the bits of an f32
+pub(crate) struct TargetLevel(AtomicU32);
+
+impl TargetLevel {
+    pub(crate) fn new(level: f32) -> Self {
+        TargetLevel(AtomicU32::new(level.to_bits()))
+    }
+
+    pub(crate) fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, level: f32) {
+        self.0.store(level.to_bits(), Ordering::Relaxed)
+    }
+}
+
+/// Moves `target` along the profile until the run is stopped, paused time does not count
+pub(crate) fn drive_load(profile: &LoadProfile, start_time: Instant, token: &CancellationToken, target: &TargetLevel) {
+    while !token.is_stopped() {
+        target.set(profile.level_at(token.active_time(start_time)));
+        thread::sleep(SCHEDULER_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(profile: &LoadProfile, seconds: f32) -> f32 {
+        profile.level_at(Duration::from_secs_f32(seconds))
+    }
+
+    #[test]
+    fn ramp_moves_linearly_then_holds() {
+        let ramp = LoadProfile::Ramp { from: 20, to: 80, over: Duration::from_secs(60) };
+        assert_eq!(at(&ramp, 0.0), 20.0);
+        assert_eq!(at(&ramp, 30.0), 50.0);
+        assert_eq!(at(&ramp, 60.0), 80.0);
+        assert_eq!(at(&ramp, 600.0), 80.0);
+
+        let down = LoadProfile::Ramp { from: 100, to: 0, over: Duration::from_secs(10) };
+        assert_eq!(at(&down, 5.0), 50.0);
+    }
+
+    #[test]
+    fn steps_clamp_at_the_last_level() {
+        let steps = LoadProfile::Steps { from: 10, to: 35, step: 10, every: Duration::from_secs(10) };
+        assert_eq!(at(&steps, 0.0), 10.0);
+        assert_eq!(at(&steps, 9.9), 10.0);
+        assert_eq!(at(&steps, 10.0), 20.0);
+        assert_eq!(at(&steps, 25.0), 30.0);
+        assert_eq!(at(&steps, 30.0), 35.0);
+
+        let down = LoadProfile::Steps { from: 90, to: 50, step: 25, every: Duration::from_secs(1) };
+        assert_eq!(at(&down, 1.0), 65.0);
+        assert_eq!(at(&down, 2.0), 50.0);
+    }
+
+    #[test]
+    fn square_is_high_for_the_first_half() {
+        let square = LoadProfile::Square { low: 0, high: 100, period: Duration::from_secs(10) };
+        assert_eq!(at(&square, 0.0), 100.0);
+        assert_eq!(at(&square, 4.9), 100.0);
+        assert_eq!(at(&square, 5.0), 0.0);
+        assert_eq!(at(&square, 10.0), 100.0);
+    }
+
+    #[test]
+    fn sine_peaks_halfway() {
+        let sine = LoadProfile::Sine { low: 20, high: 80, period: Duration::from_secs(8) };
+        assert!((at(&sine, 0.0) - 20.0).abs() < 0.01);
+        assert!((at(&sine, 2.0) - 50.0).abs() < 0.01);
+        assert!((at(&sine, 4.0) - 80.0).abs() < 0.01);
+        assert!((at(&sine, 8.0) - 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn validate_rejects_unusable_profiles() {
+        assert!(LoadProfile::Constant(0).validate().is_err());
+        assert!(LoadProfile::Constant(1).validate().is_ok());
+        assert!(LoadProfile::Constant(101).validate().is_err());
+        assert!(LoadProfile::Ramp { from: 0, to: 101, over: Duration::from_secs(10) }.validate().is_err());
+        assert!(LoadProfile::Steps { from: 10, to: 50, step: 0, every: Duration::from_secs(10) }.validate().is_err());
+        assert!(LoadProfile::Steps { from: 50, to: 50, step: 0, every: Duration::from_secs(10) }.validate().is_ok());
+        // a quarter of the period has to cover a whole duty cycle
+        assert!(LoadProfile::Square { low: 0, high: 100, period: DUTY_CYCLE_PERIOD * 4 }.validate().is_ok());
+        assert!(LoadProfile::Square { low: 0, high: 100, period: DUTY_CYCLE_PERIOD * 3 }.validate().is_err());
+        assert!(LoadProfile::Ramp { from: 0, to: 100, over: DUTY_CYCLE_PERIOD / 2 }.validate().is_err());
+    }
+}
@@ -1,27 +1,45 @@
 extern crate core;
 
-pub mod stressors;
-pub mod sensors;
-mod reporting;
-mod components;
+mod commands;
 mod prompt;
+mod tui;
 
-use std::io::{stdout, Write};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::{panic, thread};
+use std::{env, panic, thread};
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::any::Any;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use clap::{Parser, Subcommand};
 use colored::Colorize;
-use inquire::{Confirm, CustomType, MultiSelect, Select};
+use inquire::{Confirm, CustomType, MultiSelect, Select, Text};
 use inquire::error::InquireResult;
 
 use ocl::{Device, DeviceType, Platform};
 use ocl::core::DeviceInfo;
 use sysinfo::{System, SystemExt};
-use crate::components::GreetingValues;
-use crate::reporting::{prettify_output, watch_in_background};
-use crate::stressors::{OPENCL_FLOAT_ADD, OPENCL_MATRIX_MULTIPLICATION, OPENCL_SQUARE_ROOT, OPENCL_VECTOR_SIZE, OpenCLContext, OpenCLProgram, Stressor};
+use ezstress::{CancellationToken, Component, Job, RunConfig, StopReason, StressSession};
+use ezstress::components::GreetingValues;
+use ezstress::reporting::{BaselineSettings, StabilizationCriteria, TerminalPrinter, TerminationConditions};
+use ezstress::sensors;
+use ezstress::stressors::{Stressor, StressorRegistry};
+use ezstress::benchmark::{benchmark_tests, BenchmarkReport, ReferenceBaseline, BENCHMARK_TEST_DURATION};
+use ezstress::events::EventBus;
+use ezstress::history::{record_samples, HistorySample, RunHistory};
+use ezstress::job::pretty_print_int;
+use ezstress::metrics::{MetricsExporter, DEFAULT_DAEMON_REST, DEFAULT_DAEMON_RUN_DURATION};
+use ezstress::reporting::watch_idle;
+use ezstress::web::WebDashboard;
+use ezstress::watchdog::{DEFAULT_THERMAL_CEILING, THERMAL_CUTOFF_EXIT_CODE};
+use ezstress::affinity::{format_cpu_list, parse_cpu_list, PinningStrategy};
+use ezstress::load::LoadProfile;
+use ezstress::scaling::{scaling_steps, ScalingReport, ScalingTest, DEFAULT_SCALING_DURATION};
+use ezstress::stressors::disk::{DiskAccess, DiskSettings, DiskStressor, DEFAULT_FILE_SIZE};
+use ezstress::stressors::cache::{cache_stressors, detect_caches, format_size, CacheAccess, DEFAULT_CACHE_TEST_DURATION};
+use ezstress::sweep::{CoreResult, CoreSweep, DEFAULT_SWEEP_DURATION, SWEEP_FAILURE_EXIT_CODE};
+use crate::commands::{open_history, run_compare_command, run_history_command, CompareArgs, HistoryCommand, REGRESSION_EXIT_CODE};
+use crate::prompt::StressorOption;
 
 const NO_OPENCL_STRING: &str = r#"No OpenCL platforms found. This is probably because you dont have a GPU or you dont have GPU compatible drivers installed.
 If you have a GPU and the drivers are installed, please report this issue to the developers.
@@ -35,9 +53,185 @@ Nvidia GPUs:
 
 You can report this issue here: https://github.com/day-mon/easy-stress-rs"#;
 
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
 
+    /// Temperature (in Celsius) that aborts every test, used for sensors without their own critical threshold
+    #[arg(long, default_value_t = DEFAULT_THERMAL_CEILING)]
+    thermal_ceiling: f32,
+
+    /// Do not print the live time and temperature line while a test runs
+    #[arg(long)]
+    quiet: bool,
+
+    /// Print a single status line instead of the full-screen dashboard
+    #[arg(long)]
+    no_tui: bool,
+
+    /// Serve a live web dashboard on this address, e.g. 127.0.0.1:7878
+    #[arg(long, value_name = "ADDRESS")]
+    web_listen: Option<SocketAddr>,
+
+    /// Export Prometheus metrics on this address, e.g. 127.0.0.1:9464
+    #[arg(long, value_name = "ADDRESS")]
+    metrics_listen: Option<SocketAddr>,
+
+    /// Do not save finished runs to the run history
+    #[arg(long)]
+    no_history: bool,
+
+    /// Also save the temperature and throughput time series of every run to the history
+    #[arg(long)]
+    record_samples: bool,
+
+    /// Run history database to use instead of the one in the user data directory
+    #[arg(long, value_name = "PATH", global = true)]
+    history_db: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Look back at previous runs
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Check a run against an earlier run of the same stressor, exits with code 4 on a regression
+    Compare(CompareArgs),
+    /// Run the fixed benchmark suite single-threaded and on every thread and score it against the reference machine
+    Benchmark {
+        /// Threads for the all-threads half of the suite, defaults to every logical core
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Run one verified thread on every logical core in turn to find an unstable core, exits with
+    /// code 5 when a core produced errors or could not be tested
+    Sweep {
+        /// Verifiable stressor to run on every core
+        #[arg(long, default_value = "Primes")]
+        stressor: String,
+        /// How long every core is tested, in seconds
+        #[arg(long, default_value_t = DEFAULT_SWEEP_DURATION.as_secs(), value_parser = clap::value_parser!(u64).range(1..))]
+        seconds: u64,
+        /// Only test these cores, e.g. 0-3,8
+        #[arg(long, value_name = "LIST")]
+        cpus: Option<String>,
+    },
+    /// Measure the latency and bandwidth of every cache level on one thread
+    Cache {
+        /// How long every measurement runs, in seconds
+        #[arg(long, default_value_t = DEFAULT_CACHE_TEST_DURATION.as_secs(), value_parser = clap::value_parser!(u64).range(1..))]
+        seconds: u64,
+    },
+    /// Run a stressor over and over with idle time in between until stopped with Control+C, and
+    /// export every run on the address of --metrics-listen
+    Daemon {
+        /// Stressor every run uses
+        #[arg(long, default_value = "Primes")]
+        stressor: String,
+        /// How long every run lasts, in seconds
+        #[arg(long, default_value_t = DEFAULT_DAEMON_RUN_DURATION.as_secs(), value_parser = clap::value_parser!(u64).range(1..))]
+        seconds: u64,
+        /// Idle time between two runs, in seconds
+        #[arg(long, default_value_t = DEFAULT_DAEMON_REST.as_secs())]
+        rest: u64,
+        /// Threads of every run, defaults to every logical core
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Run a stressor on 1, 2, 4, ... threads and show how the throughput scales
+    Scale {
+        /// Stressor to run at every thread count
+        #[arg(long, default_value = "Primes")]
+        stressor: String,
+        /// How long every thread count runs, in seconds
+        #[arg(long, default_value_t = DEFAULT_SCALING_DURATION.as_secs(), value_parser = clap::value_parser!(u64).range(1..))]
+        seconds: u64,
+        /// Highest thread count, defaults to every logical core
+        #[arg(long)]
+        max_threads: Option<usize>,
+        /// Also write the results to this CSV file
+        #[arg(long, value_name = "PATH")]
+        csv: Option<PathBuf>,
+    },
+}
 
 fn main() -> InquireResult<()> {
+    let mut args = Args::parse();
+
+    // Control+C cancels the test that is running instead of killing the whole program
+    let active_run: Arc<Mutex<Option<CancellationToken>>> = Arc::default();
+    let handler_run = active_run.clone();
+    let _ = ctrlc::set_handler(move || match handler_run.lock().unwrap().as_ref() {
+        Some(token) => {
+            token.cancel();
+        }
+        None => std::process::exit(130),
+    });
+
+    if let Some(command) = args.command.take() {
+        // the exit code of a command that ran, 0 when it passed
+        let result = match command {
+            Command::History { command } => open_history(args.history_db.as_deref())
+                .and_then(|history| run_history_command(command, &history))
+                .map(|_| 0),
+            Command::Compare(compare) => open_history(args.history_db.as_deref())
+                .and_then(|history| run_compare_command(compare, &history))
+                .map(|passed| if passed { 0 } else { REGRESSION_EXIT_CODE }),
+            Command::Benchmark { threads } => run_benchmark(threads, args.thermal_ceiling, &active_run).map(|_| 0),
+            Command::Cache { seconds } => run_cache_test(Duration::from_secs(seconds), args.thermal_ceiling, &active_run).map(|_| 0),
+            Command::Scale { stressor, seconds, max_threads, csv } => {
+                run_scaling(&stressor, Duration::from_secs(seconds), max_threads, csv.as_deref(), args.thermal_ceiling, &active_run).map(|_| 0)
+            }
+            Command::Daemon { stressor, seconds, rest, threads } => {
+                let schedule = (Duration::from_secs(seconds), Duration::from_secs(rest));
+                run_daemon(&stressor, schedule, threads, args.metrics_listen, args.thermal_ceiling, &active_run).map(|_| 0)
+            }
+            Command::Sweep { stressor, seconds, cpus } => run_sweep(&stressor, Duration::from_secs(seconds), cpus.as_deref(), args.thermal_ceiling, &active_run)
+                .map(|passed| if passed { 0 } else { SWEEP_FAILURE_EXIT_CODE }),
+        };
+        match result {
+            Ok(0) => return Ok(()),
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                println!("{}", e.red());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let web = args.web_listen.map(|address| match WebDashboard::start(address) {
+        Ok(web) => {
+            println!("Web dashboard running on http://{address}");
+            web
+        }
+        Err(e) => {
+            println!("{}", e.red());
+            std::process::exit(1);
+        }
+    });
+
+    let metrics = args.metrics_listen.map(|address| match MetricsExporter::start(address) {
+        Ok(metrics) => {
+            println!("Prometheus metrics available on http://{address}/metrics");
+            metrics
+        }
+        Err(e) => {
+            println!("{}", e.red());
+            std::process::exit(1);
+        }
+    });
+
+    let mut history = match args.no_history {
+        true => None,
+        false => open_history(args.history_db.as_deref())
+            .inspect_err(|e| println!("{}", format!("⚠ Runs will not be saved: {e}").yellow()))
+            .ok(),
+    };
+
     println!("Looking for GPU Platforms...");
     let platforms = setup();
 
@@ -51,9 +245,8 @@ fn main() -> InquireResult<()> {
 
     println!("\rGrabbing System Information...");
     let mut sys = System::new_all();
-    let mut gpu_ctx: Option<OpenCLContext> = None;
-    let gpu_device: Option<Device> = None;
 
+    let registry = StressorRegistry::with_builtins();
     let system_information = GreetingValues::new(&sys, &platform);
     println!("{system_information}");
 
@@ -84,7 +277,7 @@ fn main() -> InquireResult<()> {
             .prompt()?;
 
 
-        let temperature = match termination_method.iter().any(|&i| i == "Temperature") {
+        let temperature = match termination_method.contains(&"Temperature") {
             true => {
                 let current_temperature = sensors::cpu_temp(&mut sys, true);
                 CustomType::<u8>::new("What temperature would you like to stop at? (In Celsius)")
@@ -98,7 +291,7 @@ fn main() -> InquireResult<()> {
             false => None
         };
 
-        let duration = match termination_method.iter().any(|&i| i == "Time") {
+        let duration = match termination_method.contains(&"Time") {
             true => CustomType::<u16>::new("How long would you like the stress test to last? (in minutes)")
                 .with_default(1)
                 .with_validator(prompt::duration_validator)
@@ -108,51 +301,147 @@ fn main() -> InquireResult<()> {
                 .ok(),
             false => None
         };
-        let method = Select::new("What method would you like to use?", get_stressors(main_question))
-            .prompt()?;
+        let stop_on_fan_failure = termination_method.contains(&"Fan Failure");
+
+        let stabilization = match termination_method.contains(&"Thermal Stabilization") {
+            true => {
+                let tolerance = CustomType::<f32>::new("How much may the temperature vary to be considered stable? (In Celsius)")
+                    .with_default(1.0)
+                    .with_validator(prompt::tolerance_validator)
+                    .with_error_message("Please type a valid number")
+                    .prompt()?;
+                let window = CustomType::<u16>::new("For how long does it have to stay within that range? (in minutes)")
+                    .with_default(2)
+                    .with_validator(prompt::duration_validator)
+                    .with_help_message("Type in a number between 1 -> 65535")
+                    .with_error_message("This number is too big. Number has to be in the range 1 -> 65535.")
+                    .prompt()?;
+                Some(StabilizationCriteria { tolerance, window: Duration::from_secs(window as u64 * 60) })
+            }
+            false => None
+        };
+
+        let min_available_memory = match termination_method.contains(&"Low Memory") {
+            true => {
+                let total_memory_mb = system_information.memory / 1024 / 1024;
+                let threshold = CustomType::<u64>::new("Below how much available memory should the test stop? (in MB)")
+                    .with_default(512)
+                    .with_validator(move |input: &u64| prompt::memory_threshold_validator(input, total_memory_mb))
+                    .with_error_message("Please type a valid number")
+                    .prompt()?;
+                Some(threshold * 1024 * 1024)
+            }
+            false => None
+        };
+
+        let (method, queue_depth) = match main_question {
+            "Disk" => match prompt_disk_settings()? {
+                Ok((stressor, queue_depth)) => (Arc::new(stressor) as Arc<dyn Stressor>, Some(queue_depth)),
+                Err(e) => {
+                    println!("{}", e.red());
+                    continue
+                }
+            },
+            _ => {
+                let StressorOption(method) = Select::new("What method would you like to use?", get_stressors(&registry, main_question))
+                    .with_formatter(&|option| option.value.0.name().to_string())
+                    .prompt()?;
+                (method, None)
+            }
+        };
+
+        let verify = match method.verifiable() {
+            true => Confirm::new("Would you like to verify the results of every iteration?")
+                .with_default(true)
+                .with_help_message("Mismatches are counted as errors, they usually mean your system is unstable")
+                .prompt()?,
+            false => false
+        };
+
+        let baseline = match main_question == "CPU" && sensors::cpu_temp(&mut sys, true).is_some() {
+            true => prompt_baseline_settings()?,
+            false => None
+        };
+
+        let pinning = match main_question {
+            "CPU" => prompt_pinning()?,
+            _ => None
+        };
+
+        let load_profile = match main_question {
+            "CPU" => prompt_load_profile()?,
+            _ => None
+        };
 
         let duration = duration.map(|dur| Duration::from_secs(dur as u64 * 60));
 
 
-        if main_question == "CPU"
-        {
-            let cpus = cpu_questions
-                .expect("CPU Option was chosen and no cpu count was given. We gotta go bye bye.");
-            match do_cpu_work(method, cpus, temperature, duration, &mut sys) {
-                Ok(job) => println!("{job}"),
-                Err(e) => println!("{e}"),
+        let component = match main_question {
+            "GPU" => {
+                let gpu_text = gpu_question.expect("GPU Option was chosen and no gpu was given. We gotta go bye bye.");
+                let device = Device::from(*{
+                    let gpu_options = get_gpu_options(&platform)
+                        .expect("Couldn't get GPU options. Something went wrong.");
+
+                    gpu_options
+                        .into_iter()
+                        .find(|&gpu| {
+                            match gpu.info(DeviceInfo::Name) {
+                                Ok(name) => name.to_string() == *gpu_text,
+                                Err(_) => false,
+                            }
+                        })
+                        .expect("Couldn't find GPU device. Something went wrong.")
+                });
+                Component::Gpu(device)
             }
+            _ => Component::Cpu
+        };
+
+        let conditions = TerminationConditions {
+            duration,
+            stop_temperature: temperature,
+            stop_on_fan_failure,
+            stabilization,
+            min_available_memory,
+        };
+
+        let mut builder = StressSession::builder()
+            .component(component)
+            .stressor(method)
+            .termination(conditions)
+            .verify(verify)
+            .thermal_ceiling(args.thermal_ceiling);
+
+        if let Some(threads) = cpu_questions.or(queue_depth) {
+            builder = builder.threads(threads);
+        }
+        if let Some(baseline) = baseline {
+            builder = builder.baseline(baseline);
+        }
+        if let Some(pinning) = pinning {
+            builder = builder.pinning(pinning);
+        }
+        if let Some(load_profile) = load_profile {
+            builder = builder.load_profile(load_profile);
         }
-        else if main_question == "GPU"
-        {
-            let gpu_text = gpu_question.expect("GPU Option was chosen and no gpu was given. We gotta go bye bye.");
-            let device = gpu_device.unwrap_or_else(|| Device::from(*{
-                let gpu_options = get_gpu_options(&platform)
-                    .expect("Couldn't get GPU options. Something went wrong.");
-
-                gpu_options
-                    .into_iter()
-                    .find(|&gpu| {
-                        match gpu.info(DeviceInfo::Name) {
-                            Ok(name) => name.to_string() == *gpu_text,
-                            Err(_) => false,
-                        }
-                    })
-                    .expect("Couldn't find GPU device. Something went wrong.")
-            }));
-
-            if gpu_ctx.is_none() {
-                gpu_ctx = OpenCLContext::new(device)
-                    .ok();
-            }
-            let output = gpu_ctx.as_ref().and_then(|ctx| {
-                get_opencl_program(&method, ctx)
-                    .and_then(|program| do_gpu_work(program, duration, method))
-                    .map(|job| format!("{job}"))
-                    .ok()
-            }).unwrap_or_else(|| "Could not get GPU context. Something went wrong.".to_string());
 
-            println!("{output}");
+        match builder.build() {
+            Ok(session) => {
+                let config = session.config();
+                let samples = (args.record_samples && history.is_some()).then(|| record_samples(session.subscribe()));
+                // errors during the run are shown by the printer or dashboard
+                let result = run_with_printer(session, &active_run, output_mode(&args), web.as_ref(), metrics.as_ref());
+                let samples = samples.and_then(|samples| samples.join().ok()).unwrap_or_default();
+                if let Ok(job) = result {
+                    println!("{job}");
+                    if let Some(history) = history.as_mut() {
+                        save_run(history, &config, &system_information, &job, &samples);
+                    }
+                    exit_on_thermal_cutoff(&job);
+                }
+            }
+            Err(e) => println!("{e}"),
         }
 
 
@@ -166,208 +455,515 @@ fn main() -> InquireResult<()> {
     Ok(())
 }
 
-fn get_stressed_components(sys_info: &GreetingValues) -> Vec<&str> {
-    if sys_info.gpu_information.is_empty() {
-        vec!["CPU"]
-    } else {
-        vec!["CPU", "GPU"]
+fn prompt_baseline_settings() -> InquireResult<Option<BaselineSettings>> {
+    let measure = Confirm::new("Would you like to measure an idle baseline before and a cooldown after the test?")
+        .with_default(false)
+        .with_help_message("Records the idle temperature, how far above idle the load pushed it and how long it takes to cool back down")
+        .prompt()?;
+
+    if !measure {
+        return Ok(None)
     }
+
+    let idle_seconds = CustomType::<u16>::new("How long should the idle baseline be measured? (in seconds)")
+        .with_default(30)
+        .with_validator(prompt::seconds_validator)
+        .with_error_message("Please type a valid number")
+        .prompt()?;
+    let cooldown_tolerance = CustomType::<f32>::new("How close to idle does the temperature have to get to count as cooled down? (In Celsius)")
+        .with_default(2.0)
+        .with_validator(prompt::tolerance_validator)
+        .with_error_message("Please type a valid number")
+        .prompt()?;
+    let cooldown_minutes = CustomType::<u16>::new("How long should the cooldown be watched at most? (in minutes)")
+        .with_default(10)
+        .with_validator(prompt::duration_validator)
+        .with_error_message("This number is too big. Number has to be in the range 1 -> 65535.")
+        .prompt()?;
+
+    Ok(Some(BaselineSettings {
+        idle_duration: Duration::from_secs(idle_seconds as u64),
+        cooldown_tolerance,
+        cooldown_timeout: Duration::from_secs(cooldown_minutes as u64 * 60),
+    }))
 }
 
-fn get_stressors(
-    choice: &str
-) -> Vec<Stressor> {
-    match choice {
-        "CPU" => vec![Stressor::Fibonacci, Stressor::FloatAddition, Stressor::FloatMultiplication, Stressor::MatrixMultiplication, Stressor::SquareRoot, Stressor::Primes, Stressor::QuakeInverseSquareRoot, Stressor::FloatDivision],
-        "GPU" => vec![Stressor::SquareRoot, Stressor::MatrixMultiplication, Stressor::FloatAddition],
-        _ => panic!("Invalid stressor")
-    }
+/// The stressor and the queue depth, which is the number of worker threads. The inner error is
+/// shown before asking what to stress again.
+fn prompt_disk_settings() -> InquireResult<Result<(DiskStressor, usize), String>> {
+    let directory = Text::new("In which directory should the test files be created?")
+        .with_default(&env::temp_dir().to_string_lossy())
+        .with_help_message("Pick a directory on the drive you want to test, the files are removed afterwards")
+        .prompt()?;
+    let file_size = CustomType::<u64>::new("How large should the file of every thread be? (in MB)")
+        .with_default(DEFAULT_FILE_SIZE / 1024 / 1024)
+        .with_help_message("Use more than the drive's cache to measure the drive and not its cache")
+        .with_validator(prompt::file_size_validator)
+        .with_error_message("Please type a valid number")
+        .prompt()?;
+    let block_size = Select::new("How large should every read and write be?", vec!["4 KiB", "64 KiB", "1 MiB"])
+        .prompt()?;
+    let access = Select::new("How should the blocks be accessed?", vec!["Random", "Sequential"])
+        .prompt()?;
+    let read_percent = CustomType::<u8>::new("Which share of the operations should be reads? (in percent)")
+        .with_default(50)
+        .with_validator(prompt::load_validator)
+        .with_error_message("Please type a number between 0 and 100")
+        .prompt()?;
+    let direct = cfg!(target_os = "linux") && Confirm::new("Would you like to bypass the page cache? (O_DIRECT)")
+        .with_default(true)
+        .with_help_message("Without it reads may come from memory instead of the drive")
+        .prompt()?;
+    let fsync = Confirm::new("Would you like to fsync after every write?")
+        .with_default(true)
+        .with_help_message("Makes every write wait until the drive has stored it")
+        .prompt()?;
+    let queue_depth = CustomType::<usize>::new("Which queue depth should be used? (operations in flight at once)")
+        .with_default(4)
+        .with_help_message("Every operation in flight is a thread with its own file doing synchronous I/O")
+        .with_validator(prompt::queue_depth_validator)
+        .with_error_message("Please type a valid number")
+        .prompt()?;
+
+    let settings = DiskSettings {
+        directory: PathBuf::from(directory),
+        file_size: file_size.checked_mul(1024 * 1024).expect("The size was validated"),
+        block_size: match block_size {
+            "64 KiB" => 64 * 1024,
+            "1 MiB" => 1024 * 1024,
+            _ => 4 * 1024,
+        },
+        access: match access {
+            "Sequential" => DiskAccess::Sequential,
+            _ => DiskAccess::Random,
+        },
+        read_percent,
+        direct,
+        fsync,
+    };
+    Ok(DiskStressor::new(settings).map(|stressor| (stressor, queue_depth)))
 }
 
-fn get_termination_options(sys: &mut System, chosen_component: &str) -> Vec<&'static str> {
-    if let ("CPU", true) = (chosen_component, sensors::cpu_temp(sys, true).is_some()) {
-        vec!["Time", "Temperature"]
-    } else {
-        vec!["Time"]
-    }
+fn prompt_load_profile() -> InquireResult<Option<LoadProfile>> {
+    let options = vec!["Constant", "Linear ramp", "Steps", "Square wave bursts", "Sine wave"];
+    let choice = Select::new("What load pattern should the threads follow?", options)
+        .with_help_message("Below 100% the threads alternate between work and sleep, sudden load steps expose VRM and power supply instability")
+        .prompt()?;
+
+    let level = |message: &str, default: u8| CustomType::<u8>::new(message)
+        .with_default(default)
+        .with_validator(prompt::load_validator)
+        .with_error_message("Please type a number between 0 and 100")
+        .prompt();
+    let seconds = |message: &str, default: u16| CustomType::<u16>::new(message)
+        .with_default(default)
+        .with_validator(prompt::seconds_validator)
+        .with_error_message("This number is too big. Number has to be in the range 1 -> 65535.")
+        .prompt()
+        .map(|seconds| Duration::from_secs(seconds as u64));
+
+    let profile = match choice {
+        "Linear ramp" => LoadProfile::Ramp {
+            from: level("Which load should the ramp start at? (in percent)", 0)?,
+            to: level("Which load should the ramp end at? (in percent)", 100)?,
+            over: seconds("How long should the ramp take? (in seconds)", 600)?,
+        },
+        "Steps" => LoadProfile::Steps {
+            from: level("Which load should the first step be? (in percent)", 10)?,
+            to: level("Which load should the last step be? (in percent)", 100)?,
+            step: level("How much should every step add? (in percent)", 10)?,
+            every: seconds("How long should every step last? (in seconds)", 60)?,
+        },
+        "Square wave bursts" => LoadProfile::Square {
+            low: level("What load should the off phase have? (in percent)", 0)?,
+            high: level("What load should the burst have? (in percent)", 100)?,
+            period: seconds("How long is one burst and pause together? (in seconds)", 10)?,
+        },
+        "Sine wave" => LoadProfile::Sine {
+            low: level("What is the lowest load of the wave? (in percent)", 0)?,
+            high: level("What is the highest load of the wave? (in percent)", 100)?,
+            period: seconds("How long is one wave? (in seconds)", 60)?,
+        },
+        _ => {
+            let percent = CustomType::<u8>::new("How much load should every thread put on its core? (in percent)")
+                .with_default(100)
+                .with_validator(prompt::constant_load_validator)
+                .with_error_message("Please type a number between 1 and 100")
+                .prompt()?;
+            match percent {
+                100 => return Ok(None),
+                percent => LoadProfile::Constant(percent),
+            }
+        }
+    };
+    Ok(Some(profile))
+}
+
+fn prompt_pinning() -> InquireResult<Option<PinningStrategy>> {
+    let options = vec!["Let the OS decide", "Physical cores first", "SMT siblings together", "Explicit CPU list", "One NUMA node"];
+    let choice = Select::new("Where should the threads run?", options)
+        .with_help_message("Pinning keeps every thread on one logical CPU, the mapping is shown in the results")
+        .prompt()?;
+
+    let strategy = match choice {
+        "Physical cores first" => PinningStrategy::PhysicalCoresFirst,
+        "SMT siblings together" => PinningStrategy::SmtSiblings,
+        "Explicit CPU list" => {
+            let list = Text::new("Which CPUs should the threads run on? (e.g. 0-3,8)")
+                .with_validator(prompt::cpu_list_validator)
+                .prompt()?;
+            PinningStrategy::Explicit(parse_cpu_list(&list).expect("The list was validated"))
+        }
+        "One NUMA node" => PinningStrategy::NumaNode(
+            CustomType::<usize>::new("Which NUMA node should the threads run on?")
+                .with_default(0)
+                .with_error_message("Please type a valid number")
+                .prompt()?
+        ),
+        _ => return Ok(None),
+    };
+    Ok(Some(strategy))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    Dashboard,
+    StatusLine,
+    Quiet,
 }
 
-fn get_stressor_functions(
-    stressor: &Stressor
-) -> fn() {
-    match stressor {
-        Stressor::Fibonacci => stressors::fibonacci_cpu,
-        Stressor::Primes => stressors::primes,
-        Stressor::MatrixMultiplication => stressors::matrix_multiplication,
-        Stressor::FloatAddition => stressors::float_add,
-        Stressor::FloatMultiplication => stressors::float_mul,
-        Stressor::SquareRoot => || { stressors::sqrt_cpu(std::hint::black_box(1_143_243_423.112_354_3)) },
-        Stressor::FloatDivision => stressors::float_division,
-        Stressor::QuakeInverseSquareRoot => || { stressors::quake_rsqrt(std::hint::black_box(1_143_243_423.112_354_3)) }
+fn output_mode(args: &Args) -> OutputMode {
+    let dumb_terminal = env::var("TERM").is_ok_and(|term| term == "dumb");
+    match args {
+        Args { quiet: true, .. } => OutputMode::Quiet,
+        Args { no_tui: true, .. } => OutputMode::StatusLine,
+        _ if dumb_terminal || !std::io::stdout().is_terminal() => OutputMode::StatusLine,
+        _ => OutputMode::Dashboard,
     }
 }
 
-pub fn get_opencl_program(
-    method: &Stressor,
-    ctx: &OpenCLContext,
-) -> Result<OpenCLProgram, String> {
-    match method {
-        Stressor::SquareRoot => {
-            // yeah, lets spam sqrt 952 on gpu
-            let sqrt_vector = vec![1_000_000.94_f32; OPENCL_VECTOR_SIZE];
-            let result_vector = vec![0_f32; OPENCL_VECTOR_SIZE];
-            OpenCLProgram::new(ctx, OPENCL_SQUARE_ROOT, "sqrt", vec![sqrt_vector, result_vector])
-        }
-        Stressor::FloatAddition => {
-            let f_add_vector = vec![952.139_1_f32; OPENCL_VECTOR_SIZE];
-            let result_vector = vec![0_f32; OPENCL_VECTOR_SIZE];
-            OpenCLProgram::new(ctx, OPENCL_FLOAT_ADD, "float_add", vec![f_add_vector, result_vector])
-        }
-        Stressor::MatrixMultiplication => {
-            let matrix_a = vec![201_231_231.231139_13_f32; OPENCL_VECTOR_SIZE];
-            let matrix_b = vec![231_231.23_f32; OPENCL_VECTOR_SIZE];
-            let result_vector = vec![0_f32; OPENCL_VECTOR_SIZE];
-            OpenCLProgram::new(ctx, OPENCL_MATRIX_MULTIPLICATION, "matrix_mult", vec![matrix_a, matrix_b, result_vector])
-        }
-        _ => {
-            println!("No method found, defaulting to sqrt");
-            let sqrt_vector = vec![952_f32; OPENCL_VECTOR_SIZE];
-            let result_vector = vec![0_f32; OPENCL_VECTOR_SIZE];
-            OpenCLProgram::new(ctx, OPENCL_SQUARE_ROOT, "sqrt", vec![sqrt_vector, result_vector])
+fn run_with_printer(
+    session: StressSession,
+    active_run: &Mutex<Option<CancellationToken>>,
+    mode: OutputMode,
+    web: Option<&WebDashboard>,
+    metrics: Option<&MetricsExporter>,
+) -> Result<Job, String> {
+    let web_feed = web.map(|web| web.attach(session.subscribe()));
+    let metrics_feed = metrics.map(|metrics| metrics.attach(session.subscribe()));
+    let events = session.subscribe();
+    let token = session.cancellation_token();
+    let printer = thread::spawn(move || match mode {
+        OutputMode::Dashboard => {
+            if let Err(e) = tui::run_dashboard(events, token) {
+                println!("{}", format!("Dashboard failed: {e}").red());
+            }
         }
+        mode => TerminalPrinter::new(mode == OutputMode::StatusLine).consume(events),
+    });
+    *active_run.lock().unwrap() = Some(session.cancellation_token());
+    let result = session.run();
+    *active_run.lock().unwrap() = None;
+    // dropping the session disconnects the printer in case the run failed before finishing
+    drop(session);
+    let _ = printer.join();
+    for feed in web_feed.into_iter().chain(metrics_feed) {
+        let _ = feed.join();
     }
+    result
 }
 
-fn get_gpu_options(platform: &Option<Platform>) -> Option<Vec<Device>> {
-    platform
-        .map(|plat| Device::list(plat, Some(DeviceType::GPU)).ok())
-        .flatten()
+fn run_benchmark(threads: Option<usize>, thermal_ceiling: f32, active_run: &Mutex<Option<CancellationToken>>) -> Result<(), String> {
+    let threads = threads
+        .or_else(|| thread::available_parallelism().ok().map(|threads| threads.get()))
+        .unwrap_or(1);
+    let registry = StressorRegistry::with_builtins();
+    let reference = ReferenceBaseline::shipped();
+    let tests = benchmark_tests(threads, thermal_ceiling);
+    let total_time = BENCHMARK_TEST_DURATION * tests.len() as u32;
+
+    println!("Running {} benchmark tests, this takes about {} minutes", tests.len(), total_time.as_secs().div_ceil(60));
+    let mut jobs = Vec::with_capacity(tests.len());
+    for (index, test) in tests.iter().enumerate() {
+        println!("\n[{}/{}] {} on {} thread(s)", index + 1, tests.len(), test.stressor, test.threads);
+        let job = run_with_printer(test.session(&registry)?, active_run, OutputMode::StatusLine, None, None)?;
+        if job.stop_reason != StopReason::TimeLimit {
+            exit_on_thermal_cutoff(&job);
+            return Err(format!("Benchmark aborted: {}", job.stop_reason));
+        }
+        jobs.push(job);
+    }
 
+    let report = BenchmarkReport::new(&tests, &jobs, &reference)?;
+    println!("\n{}", "Benchmark Results".bold());
+    println!("Scores are relative to one core of the reference machine ({}) = 1000\n", reference.machine);
+    println!("{:<28}  {:>8}  {:>18}  {:>8}", "Stressor", "Threads", "Throughput/s", "Score");
+    for result in &report.results {
+        println!("{:<28}  {:>8}  {:>18}  {:>8.0}", result.stressor, result.threads,
+                 pretty_print_int(result.throughput as u64), result.score);
+    }
+    println!("\n⇁ Single-Thread Score: {}", format!("{:.0}", report.single_thread_score).bold());
+    println!("⇁ Multi-Thread Score ({threads} threads): {}", format!("{:.0}", report.multi_thread_score).bold());
+    Ok(())
 }
 
-fn do_gpu_work(
-    program: OpenCLProgram,
-    duration: Option<Duration>,
-    method: Stressor,
-) -> Result<Job, String> {
-    println!("{}", format!("🏁 Starting {method}. If you wish to stop the test at any point hold Control+C").white().bold());
-    let start_time = Instant::now();
-    let mut iterations = 0;
-
-    program.run()
-        .map_err(|error| format!("Some error has occurred while trying to do a test run to see if {method} runs on your computer. Error: {error}"))?;
+/// Returns false when a core produced errors or the worker could not be pinned to it
+fn run_sweep(
+    stressor: &str,
+    duration: Duration,
+    cpus: Option<&str>,
+    thermal_ceiling: f32,
+    active_run: &Mutex<Option<CancellationToken>>,
+) -> Result<bool, String> {
+    let stressor = StressorRegistry::with_builtins().find(stressor)
+        .ok_or_else(|| format!("There is no stressor called {stressor}"))?;
+    let mut sweep = CoreSweep::new(stressor, duration, thermal_ceiling)?;
+    if let Some(cpus) = cpus {
+        sweep.only(parse_cpu_list(cpus)?)?;
+    }
 
+    let total_time = duration * sweep.cpus.len() as u32;
+    println!("Sweeping {} cores with {}, this takes about {} minutes", sweep.cpus.len(), sweep.stressor, total_time.as_secs().div_ceil(60));
+    let mut results = Vec::with_capacity(sweep.cpus.len());
+    for (index, &cpu) in sweep.cpus.iter().enumerate() {
+        println!("\n[{}/{}] CPU {cpu}", index + 1, sweep.cpus.len());
+        let job = run_with_printer(sweep.session(cpu)?, active_run, OutputMode::StatusLine, None, None)?;
+        let aborted = job.stop_reason != StopReason::TimeLimit;
+        results.push(CoreResult::new(cpu, &job));
+        // the cores tested so far are still worth showing
+        if aborted {
+            println!("{}", format!("Sweep aborted: {}", job.stop_reason).red());
+            exit_on_thermal_cutoff(&job);
+            break;
+        }
+    }
 
-    loop {
-        if let Some(duration) = duration {
-            if start_time.elapsed() > duration {
-                break;
-            }
+    println!("\n{}", "Per-Core Results".bold());
+    println!("{:>5}  {:>16}  {:>9}  {:>8}  {:>8}  Result", "CPU", "Throughput/s", "Relative", "Max Temp", "Errors");
+    for result in &results {
+        let max_temp = result.max_cpu_temp.map_or_else(|| String::from("-"), |temp| format!("{temp:.1}°C"));
+        if !result.pinned {
+            println!("{:>5}  {:>16}  {:>9}  {:>8}  {:>8}  {}", result.cpu, "-", "-", "-", "-", "NOT TESTED, could not pin".yellow().bold());
+            continue
         }
-        let mut iter_failed = false;
+        let verdict = match result.stable() {
+            true => "stable".green(),
+            false => "UNSTABLE".red().bold(),
+        };
+        println!("{:>5}  {:>16}  {:>8.1}%  {:>8}  {:>8}  {verdict}", result.cpu, pretty_print_int(result.throughput as u64),
+                 result.relative_throughput(&results), max_temp, pretty_print_int(result.errors));
+    }
 
+    let unstable = results.iter().filter(|result| result.pinned && !result.stable()).map(|result| result.cpu).collect::<Vec<usize>>();
+    let unpinned = results.iter().filter(|result| !result.pinned).map(|result| result.cpu).collect::<Vec<usize>>();
+    if !unstable.is_empty() {
+        println!("\n{} errors on CPU {}", "FAIL".red().bold(), format_cpu_list(&unstable));
+    }
+    if !unpinned.is_empty() {
+        println!("\n{} could not pin the worker to CPU {}, those cores were not tested", "FAIL".red().bold(), format_cpu_list(&unpinned));
+    }
+    if unstable.is_empty() && unpinned.is_empty() {
+        println!("\n{} no core produced errors", "PASS".green().bold());
+    }
+    Ok(unstable.is_empty() && unpinned.is_empty())
+}
 
-        program.run().unwrap_or_else(|_| {
-            println!("Error occurred while attempting to enqueue the kernel. If this continues to happen just Control+C");
-            iter_failed = true;
-        });
+fn run_cache_test(duration: Duration, thermal_ceiling: f32, active_run: &Mutex<Option<CancellationToken>>) -> Result<(), String> {
+    let caches = detect_caches();
+    if caches.is_empty() {
+        return Err("Could not detect the CPU caches".to_string())
+    }
 
-        if iter_failed { continue; }
+    let stressors = cache_stressors(&caches);
+    let total_time = duration * stressors.len() as u32;
+    println!("Measuring {} cache levels, this takes about {} minutes", caches.len(), total_time.as_secs().div_ceil(60));
+    let mut results = Vec::with_capacity(stressors.len());
+    for (index, stressor) in stressors.into_iter().enumerate() {
+        println!("\n[{}/{}] {}", index + 1, caches.len() * 2, stressor.name());
+        let (cache, access) = (stressor.cache, stressor.access);
+        let session = StressSession::builder()
+            .component(Component::Cpu)
+            .stressor(Arc::new(stressor))
+            .threads(1)
+            .verify(true)
+            .duration(duration)
+            .thermal_ceiling(thermal_ceiling)
+            .build()?;
+        let job = run_with_printer(session, active_run, OutputMode::StatusLine, None, None)?;
+        if job.stop_reason != StopReason::TimeLimit {
+            exit_on_thermal_cutoff(&job);
+            return Err(format!("Cache test aborted: {}", job.stop_reason));
+        }
+        results.push((cache, access, job));
+    }
 
-        iterations += 1;
+    println!("\n{}", "Cache Hierarchy".bold());
+    println!("{:>5}  {:>10}  {:>11}  {:>12}  {:>14}  {:>6}", "Level", "Size", "Working Set", "Latency", "Bandwidth", "Errors");
+    for cache in &caches {
+        let job = |access| results.iter().find(|(result, result_access, _)| result.level == cache.level && *result_access == access).map(|(_, _, job)| job);
+        let latency = job(CacheAccess::PointerChase).map_or_else(|| String::from("-"), |job| format!("{:.2} ns", 1e9 / job.throughput()));
+        let bandwidth = job(CacheAccess::Streaming).map_or_else(|| String::from("-"), |job| format!("{:.1} GB/s", job.throughput() / 1e9));
+        let errors: u64 = results.iter().filter(|(result, _, _)| result.level == cache.level).filter_map(|(_, _, job)| job.errors_detected).sum();
+        println!("{:>5}  {:>10}  {:>11}  {:>12}  {:>14}  {:>6}", format!("L{}", cache.level), format_size(cache.size),
+                 format_size(cache.working_set()), latency, bandwidth, pretty_print_int(errors));
+    }
+    Ok(())
+}
 
-        let output = prettify_output(duration, start_time, None);
-        print!("{output}");
-        let _ = stdout().flush();
+fn run_scaling(
+    stressor: &str,
+    duration: Duration,
+    max_threads: Option<usize>,
+    csv: Option<&Path>,
+    thermal_ceiling: f32,
+    active_run: &Mutex<Option<CancellationToken>>,
+) -> Result<(), String> {
+    let stressor = StressorRegistry::with_builtins().find(stressor)
+        .ok_or_else(|| format!("There is no stressor called {stressor}"))?;
+    let max_threads = match max_threads {
+        Some(0) => return Err("At least one thread is required".to_string()),
+        Some(threads) => threads,
+        None => thread::available_parallelism().map_or(1, |threads| threads.get()),
+    };
+    let test = ScalingTest { stressor, duration, thermal_ceiling };
+    let steps = scaling_steps(max_threads);
+
+    let total_time = duration * steps.len() as u32;
+    println!("Running {} on {} thread counts, this takes about {} minutes", test.stressor, steps.len(), total_time.as_secs().div_ceil(60));
+    let mut runs = Vec::with_capacity(steps.len());
+    for (index, &threads) in steps.iter().enumerate() {
+        println!("\n[{}/{}] {threads} thread(s)", index + 1, steps.len());
+        let job = run_with_printer(test.session(threads)?, active_run, OutputMode::StatusLine, None, None)?;
+        if job.stop_reason != StopReason::TimeLimit {
+            exit_on_thermal_cutoff(&job);
+            return Err(format!("Scaling test aborted: {}", job.stop_reason));
+        }
+        runs.push((threads, job));
     }
 
-    Ok(
-        Job {
-            name: method.to_string(),
-            total_iterations: iterations,
-            cpu_count: None,
-            average_cpu_temp: None,
-            min_cpu_temp: None,
-            max_cpu_temp: None,
+    let report = ScalingReport::new(&runs)?;
+    println!("\n{}", format!("{} Thread Scaling", report.stressor).bold());
+    println!("{:>7}  {:>18}  {:>8}  {:>10}  {:>13}  {:>8}", "Threads", format!("{}/s", report.unit), "Speedup", "Efficiency", "Marginal Gain", "Max Temp");
+    for step in &report.steps {
+        let marginal_gain = step.marginal_gain.map_or_else(|| String::from("-"), |gain| format!("{gain:.1}%"));
+        let max_temp = step.max_cpu_temp.map_or_else(|| String::from("-"), |temp| format!("{temp:.1}°C"));
+        println!("{:>7}  {:>18}  {:>7.2}x  {:>9.1}%  {:>13}  {:>8}", step.threads, pretty_print_int(step.throughput as u64),
+                 step.speedup, step.efficiency, marginal_gain, max_temp);
+    }
 
-            stop_reasoning: "Time limit exceeded".to_string(),
-        }
-    )
+    if let Some(path) = csv {
+        std::fs::write(path, report.to_csv())
+            .map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+        println!("\nSaved the results to {}", path.display());
+    }
+    Ok(())
 }
 
-fn do_cpu_work(
-    method: Stressor,
-    cpu_count: usize,
-    stop_temperature: Option<u8>,
-    duration: Option<Duration>,
-    system: &mut System,
-) -> Result<Job, String> {
-    let start_time = Instant::now();
-    let running = Arc::new(AtomicUsize::new(0));
-
-    let atomic_bool = running.clone();
-    let function = get_stressor_functions(&method);
-
-    println!("{}", format!("🏁 Starting {method}. If you wish to stop the test at any point hold Control+C").white().bold());
-
-
-    thread::scope(move |scope| {
-        let mut handles = Vec::with_capacity(cpu_count);
-        for _ in 0..cpu_count
-        {
-            let thread_running = running.clone();
-            let handle = scope.spawn(move ||
-                {
-                    // for the stressor functions check the asm
-                    let mut iterations: u64 = 0;
-                    while thread_running.load(Ordering::SeqCst) == 0
-                    {
-                        function();
-                        iterations += 1;
-                    }
-                    iterations
-                });
-            handles.push(handle);
+/// `schedule` is how long every run lasts and how long the daemon idles in between
+fn run_daemon(
+    stressor: &str,
+    schedule: (Duration, Duration),
+    threads: Option<usize>,
+    metrics_listen: Option<SocketAddr>,
+    thermal_ceiling: f32,
+    active_run: &Mutex<Option<CancellationToken>>,
+) -> Result<(), String> {
+    let (duration, rest) = schedule;
+    let address = metrics_listen.ok_or("The daemon exports its runs over Prometheus, please pass --metrics-listen")?;
+    let stressor = StressorRegistry::with_builtins().find(stressor)
+        .ok_or_else(|| format!("There is no stressor called {stressor}"))?;
+    let threads = threads
+        .or_else(|| thread::available_parallelism().ok().map(|threads| threads.get()))
+        .unwrap_or(1);
+    let metrics = MetricsExporter::start(address)?;
+    println!("Prometheus metrics available on http://{address}/metrics");
+    println!("Running {stressor} on {threads} thread(s) for {}s every {}s, press Control+C to stop", duration.as_secs(), (duration + rest).as_secs());
+
+    let mut system = System::new();
+    for run in 1.. {
+        println!("\n[Run {run}]");
+        let session = StressSession::builder()
+            .component(Component::Cpu)
+            .stressor(stressor.clone())
+            .threads(threads)
+            .duration(duration)
+            .thermal_ceiling(thermal_ceiling)
+            .build()?;
+        let job = run_with_printer(session, active_run, OutputMode::StatusLine, None, Some(&metrics))?;
+        exit_on_thermal_cutoff(&job);
+        if job.stop_reason == StopReason::Cancelled {
+            break
         }
 
+        // the exporter keeps reporting temperatures while the machine rests
+        let events = EventBus::default();
+        let feed = metrics.attach(events.subscribe());
+        let token = CancellationToken::new();
+        *active_run.lock().unwrap() = Some(token.clone());
+        watch_idle(&mut system, Some(rest), &token, &events);
+        *active_run.lock().unwrap() = None;
+        drop(events);
+        let _ = feed.join();
+        if token.is_cancelled() {
+            break
+        }
+    }
+    Ok(())
+}
 
-        let background_report = watch_in_background(
-            stop_temperature,
-            duration,
-            system,
-            start_time,
-            atomic_bool,
-        );
-
-        let stop_reason = match running.load(Ordering::SeqCst) {
-            1 => "Time Limit exceeded",
-            2 => "Temperature exceeded",
-            3 => "Ctrl-C caught",
-            _ => panic!("This should have never happened. {} is not a valid option", running.load(Ordering::SeqCst))
-        }.to_string();
-
-        let mut total_iterations = 0;
-        for handle in handles {
-            if let Ok(iterations) = handle.join() {
-                total_iterations += iterations;
-            } else {
-                return Err("Failed to join thread".to_string());
-            }
+fn save_run(history: &mut RunHistory, config: &RunConfig, system: &GreetingValues, job: &Job, samples: &[HistorySample]) {
+    match history.record(config, system, job, samples) {
+        Ok(id) => println!("Saved as run #{id}, see it again with `ezstress history show {id}`"),
+        Err(e) => println!("{}", format!("⚠ {e}").yellow()),
+    }
+}
+
+fn exit_on_thermal_cutoff(job: &Job) {
+    if job.thermal_trip.is_some() {
+        println!("{}", "Test was aborted by the thermal safety cutoff. Check your cooling before running again.".red().bold());
+        std::process::exit(THERMAL_CUTOFF_EXIT_CODE);
+    }
+}
+
+fn get_stressed_components(sys_info: &GreetingValues) -> Vec<&str> {
+    if sys_info.gpu_information.is_empty() {
+        vec!["CPU", "Disk"]
+    } else {
+        vec!["CPU", "GPU", "Disk"]
+    }
+}
+
+fn get_stressors(
+    registry: &StressorRegistry,
+    choice: &str
+) -> Vec<StressorOption> {
+    let stressors = match choice {
+        "CPU" => registry.cpu_stressors(),
+        "GPU" => registry.gpu_stressors(),
+        _ => panic!("Invalid stressor")
+    };
+    stressors.into_iter().map(StressorOption).collect()
+}
+
+fn get_termination_options(sys: &mut System, chosen_component: &str) -> Vec<&'static str> {
+    if let ("CPU", true) = (chosen_component, sensors::cpu_temp(sys, true).is_some()) {
+        if sensors::fan_speeds().is_empty() {
+            vec!["Time", "Temperature", "Thermal Stabilization", "Low Memory"]
+        } else {
+            vec!["Time", "Temperature", "Thermal Stabilization", "Fan Failure", "Low Memory"]
         }
+    } else if chosen_component == "CPU" {
+        vec!["Time", "Low Memory"]
+    } else {
+        vec!["Time"]
+    }
+}
 
+fn get_gpu_options(platform: &Option<Platform>) -> Option<Vec<Device>> {
+    platform
+        .map(|plat| Device::list(plat, Some(DeviceType::GPU)).ok())
+        .flatten()
 
-        Ok(
-            Job {
-                name: method.to_string(),
-                total_iterations,
-                cpu_count: Some(cpu_count),
-                stop_reasoning: stop_reason,
-                average_cpu_temp: background_report.average_cpu_temp,
-                min_cpu_temp: background_report.min_cpu_temp,
-                max_cpu_temp: background_report.max_cpu_temp,
-            }
-        )
-    })
 }
 
 pub fn setup() -> Result<Vec<Platform>, Box<dyn Any + Send + 'static>> {
@@ -379,43 +975,6 @@ pub fn setup() -> Result<Vec<Platform>, Box<dyn Any + Send + 'static>> {
     platforms
 }
 
-pub struct Job {
-    name: String,
-    total_iterations: u64,
-    cpu_count: Option<usize>,
-    average_cpu_temp: Option<f32>,
-    min_cpu_temp: Option<f32>,
-    max_cpu_temp: Option<f32>,
-    stop_reasoning: String,
-}
-
-impl std::fmt::Display for Job {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\n{} Stress Test Results ", self.name)?;
-
-        write!(f, "\n⇁ Job Name: {} \n⇁ Total Iterations: {} \n⇁ Stop Reasoning: {}",
-               self.name, pretty_print_int(self.total_iterations), self.stop_reasoning)?;
-
-        if let Some(cpus) = self.cpu_count {
-            write!(f, "\n⇁ CPU Count: {cpus}")?;
-        }
-
-        if let Some(max_temp) = self.max_cpu_temp {
-            write!(f, "\n⇁ Peak CPU Temperature: {max_temp:.2}°C")?;
-        }
-
-        if let Some(min_temp) = self.min_cpu_temp {
-            write!(f, "\n⇁ Minimum CPU Temperature: {min_temp:.2}°C")?;
-        }
-
-        if let Some(average_temp) = self.average_cpu_temp {
-            write!(f, "\n⇁ Average CPU Temperature: {average_temp:.2}°C")?;
-        }
-
-
-        Ok(())
-    }
-}
 fn obtain_platform(platforms: Vec<Platform>) -> Option<Platform> {
     match platforms.len() {
         0 => {
@@ -432,16 +991,3 @@ fn obtain_platform(platforms: Vec<Platform>) -> Option<Platform> {
         }
     }
 }
-
-fn pretty_print_int(i: u64) -> String {
-    let mut s = String::new();
-    let i_str = i.to_string();
-    let a = i_str.chars().rev().enumerate();
-    for (idx, val) in a {
-        if idx != 0 && idx % 3 == 0 {
-            s.insert(0, ',');
-        }
-        s.insert(0, val);
-    }
-    s
-}
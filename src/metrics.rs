@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+use crate::control::StopReason;
+use crate::events::{Event, Sample};
+
+/// How long every run of the daemon lasts
+pub const DEFAULT_DAEMON_RUN_DURATION: Duration = Duration::from_secs(5 * 60);
+/// Idle time between two runs of the daemon
+pub const DEFAULT_DAEMON_REST: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Default)]
+struct MetricsState {
+    running: bool,
+    stressor: Option<String>,
+    unit: Option<String>,
+    sample: Option<Sample>,
+    warnings: u64,
+    runs_finished: u64,
+    last_stop_reason: Option<StopReason>,
+    /// What finished runs added to the counters, so they keep growing from run to run
+    totals: Totals,
+}
+
+#[derive(Default)]
+struct Totals {
+    thread_iterations: Vec<u64>,
+    /// Work per unit, runs of stressors with different units can not be added up
+    work_done: BTreeMap<String, u64>,
+    errors: u64,
+}
+
+impl MetricsState {
+    /// Folds the counters of the current run into the totals and drops its readings
+    fn end_run(&mut self) {
+        self.running = false;
+        // the readings of a finished run must not look current
+        let Some(sample) = self.sample.take() else {
+            return
+        };
+        if sample.workers.is_empty() {
+            return
+        }
+        let totals = &mut self.totals;
+        if totals.thread_iterations.len() < sample.workers.len() {
+            totals.thread_iterations.resize(sample.workers.len(), 0);
+        }
+        for (total, worker) in totals.thread_iterations.iter_mut().zip(&sample.workers) {
+            *total += worker.iterations;
+        }
+        *totals.work_done.entry(self.unit.clone().unwrap_or_default()).or_default() += sample.work_done;
+        totals.errors += sample.workers.iter().map(|worker| worker.errors).sum::<u64>();
+    }
+
+    /// The sample of the running stress test, None between runs and while only idle readings come in
+    fn run_sample(&self) -> Option<&Sample> {
+        self.sample.as_ref().filter(|sample| self.running && !sample.workers.is_empty())
+    }
+}
+
+/// Serves the state of the current run in the Prometheus text format on `/metrics`.
+///
+/// Like the web dashboard it outlives single runs, [`MetricsExporter::attach`] it to the events
+/// of every session. The counters keep growing across runs while the readings of a finished run
+/// are dropped, attach it to [`watch_idle`](crate::reporting::watch_idle) between runs to keep
+/// reporting temperatures.
+pub struct MetricsExporter {
+    server: Arc<Server>,
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl MetricsExporter {
+    pub fn start(address: impl ToSocketAddrs) -> Result<MetricsExporter, String> {
+        let server = Server::http(address)
+            .map(Arc::new)
+            .map_err(|e| format!("Could not start the metrics exporter: {e}"))?;
+        let state = Arc::new(Mutex::new(MetricsState::default()));
+
+        let accept_server = server.clone();
+        let accept_state = state.clone();
+        thread::spawn(move || {
+            for request in accept_server.incoming_requests() {
+                let response = match request.url().split('?').next() {
+                    Some("/metrics") => Response::from_string(render(&accept_state.lock().unwrap()))
+                        .with_header(Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap()),
+                    _ => Response::from_string("Not Found").with_status_code(404),
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        Ok(MetricsExporter { server, state })
+    }
+
+    /// The address the server is bound to, useful when it was started on port 0
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Feeds events to the exporter until the sender hangs up
+    pub fn attach(&self, events: Receiver<Event>) -> JoinHandle<()> {
+        let state = self.state.clone();
+
+        thread::spawn(move || {
+            for event in events {
+                let mut state = state.lock().unwrap();
+                match event {
+                    Event::Started { stressor, unit, .. } => {
+                        state.running = true;
+                        state.stressor = Some(stressor);
+                        state.unit = Some(unit);
+                    }
+                    Event::Sample(sample) => state.sample = Some(sample),
+                    Event::Warning(_) => state.warnings += 1,
+                    Event::Finished(job) => {
+                        state.end_run();
+                        state.runs_finished += 1;
+                        state.last_stop_reason = Some(job.stop_reason);
+                    }
+                    Event::StageChanged(_) | Event::Error(_) => {}
+                }
+            }
+
+            // a run that failed to start never sends Finished
+            let mut state = state.lock().unwrap();
+            if state.running {
+                state.end_run();
+            }
+        })
+    }
+}
+
+impl Drop for MetricsExporter {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+fn render(state: &MetricsState) -> String {
+    let mut output = String::new();
+    let run_sample = state.run_sample();
+
+    metric(&mut output, "ezstress_running", "gauge", "Whether a stress test is running");
+    writeln!(output, "ezstress_running {}", state.running as u8).unwrap();
+
+    if let (true, Some(stressor)) = (state.running, &state.stressor) {
+        metric(&mut output, "ezstress_run_info", "gauge", "The stressor of the running stress test");
+        writeln!(output, "ezstress_run_info{{stressor=\"{}\"}} 1", escape(stressor)).unwrap();
+    }
+
+    metric(&mut output, "ezstress_runs_finished_total", "counter", "Runs finished since the exporter started");
+    writeln!(output, "ezstress_runs_finished_total {}", state.runs_finished).unwrap();
+
+    if let Some(last_stop_reason) = state.last_stop_reason {
+        metric(&mut output, "ezstress_stop_reason", "gauge", "Why the last run stopped, 1 for the reason that applies");
+        for reason in StopReason::ALL {
+            writeln!(output, "ezstress_stop_reason{{reason=\"{reason:?}\"}} {}", (reason == last_stop_reason) as u8).unwrap();
+        }
+    }
+
+    metric(&mut output, "ezstress_warnings_total", "counter", "Warnings raised since the exporter started");
+    writeln!(output, "ezstress_warnings_total {}", state.warnings).unwrap();
+
+    // the counters include the run that is going, so they do not jump back when it ends
+    let current = run_sample.map_or(&[][..], |sample| &sample.workers[..]);
+    let threads = state.totals.thread_iterations.len().max(current.len());
+    if threads > 0 {
+        metric(&mut output, "ezstress_thread_iterations_total", "counter", "Iterations per worker thread over every run");
+        for thread in 0..threads {
+            let total = state.totals.thread_iterations.get(thread).copied().unwrap_or_default()
+                + current.get(thread).map_or(0, |worker| worker.iterations);
+            writeln!(output, "ezstress_thread_iterations_total{{thread=\"{thread}\"}} {total}").unwrap();
+        }
+
+        let mut work_done = state.totals.work_done.clone();
+        if let Some(sample) = run_sample {
+            *work_done.entry(state.unit.clone().unwrap_or_default()).or_default() += sample.work_done;
+        }
+        metric(&mut output, "ezstress_work_total", "counter", "Work done over every run, in the stressor's unit");
+        for (unit, work_done) in &work_done {
+            writeln!(output, "ezstress_work_total{{unit=\"{}\"}} {work_done}", escape(unit)).unwrap();
+        }
+
+        metric(&mut output, "ezstress_errors_detected_total", "counter", "Verification failures over every run");
+        let errors = state.totals.errors + current.iter().map(|worker| worker.errors).sum::<u64>();
+        writeln!(output, "ezstress_errors_detected_total {errors}").unwrap();
+    }
+
+    let Some(sample) = &state.sample else {
+        return output
+    };
+
+    metric(&mut output, "ezstress_elapsed_seconds", "gauge", "Time since the current run or idle monitor started, without pauses");
+    writeln!(output, "ezstress_elapsed_seconds {}", sample.elapsed.as_secs_f64()).unwrap();
+
+    if let Some(remaining) = sample.remaining {
+        metric(&mut output, "ezstress_remaining_seconds", "gauge", "Time left until the current run hits its time limit");
+        writeln!(output, "ezstress_remaining_seconds {}", remaining.as_secs_f64()).unwrap();
+    }
+
+    metric(&mut output, "ezstress_temperature_celsius", "gauge", "Temperature per sensor");
+    for reading in &sample.temperatures {
+        writeln!(output, "ezstress_temperature_celsius{{sensor=\"{}\"}} {}", escape(&reading.label), reading.temperature).unwrap();
+    }
+
+    metric(&mut output, "ezstress_cpu_usage_percent", "gauge", "Load per logical core");
+    for (core, load) in sample.cores.iter().enumerate() {
+        writeln!(output, "ezstress_cpu_usage_percent{{core=\"{core}\"}} {}", load.usage).unwrap();
+    }
+
+    metric(&mut output, "ezstress_cpu_frequency_mhz", "gauge", "Frequency per logical core");
+    for (core, load) in sample.cores.iter().enumerate() {
+        writeln!(output, "ezstress_cpu_frequency_mhz{{core=\"{core}\"}} {}", load.frequency).unwrap();
+    }
+
+    if let Some(sample) = run_sample {
+        let unit = escape(state.unit.as_deref().unwrap_or("iterations"));
+        metric(&mut output, "ezstress_work_per_second", "gauge", "Throughput of the current run, in the stressor's unit");
+        writeln!(output, "ezstress_work_per_second{{unit=\"{unit}\"}} {}", sample.throughput).unwrap();
+    }
+
+    output
+}
+
+fn metric(output: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(output, "# HELP {name} {help}").unwrap();
+    writeln!(output, "# TYPE {name} {kind}").unwrap();
+}
+
+// label values may not contain raw backslashes, quotes or newlines
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{EventBus, WorkerProgress};
+    use super::*;
+
+    fn sample(iterations: u64, errors: u64) -> Event {
+        Event::Sample(Sample {
+            elapsed: Duration::from_secs(1),
+            remaining: None,
+            cpu_temp: None,
+            temperatures: vec![],
+            cores: vec![],
+            work_done: iterations,
+            workers: vec![WorkerProgress { iterations, work_done: iterations, errors }],
+            throughput: 10.0,
+        })
+    }
+
+    fn run(exporter: &MetricsExporter, events: &[Event]) {
+        let bus = EventBus::default();
+        let feed = exporter.attach(bus.subscribe());
+        bus.emit(Event::Started { stressor: String::from("Primes"), unit: String::from("iterations"), threads: Some(1) });
+        for event in events {
+            bus.emit(event.clone());
+        }
+        drop(bus);
+        feed.join().unwrap();
+    }
+
+    #[test]
+    fn run_gauges_are_cleared_when_the_run_ends() {
+        let exporter = MetricsExporter::start("127.0.0.1:0").unwrap();
+        run(&exporter, &[sample(10, 0)]);
+
+        let output = render(&exporter.state.lock().unwrap());
+        assert!(output.contains("ezstress_running 0"), "{output}");
+        assert!(!output.contains("ezstress_run_info"), "{output}");
+        assert!(!output.contains("ezstress_work_per_second"), "{output}");
+    }
+
+    #[test]
+    fn counters_keep_growing_across_runs() {
+        let exporter = MetricsExporter::start("127.0.0.1:0").unwrap();
+        run(&exporter, &[sample(10, 1)]);
+        run(&exporter, &[sample(5, 0), sample(25, 2)]);
+
+        let output = render(&exporter.state.lock().unwrap());
+        assert!(output.contains("ezstress_thread_iterations_total{thread=\"0\"} 35"), "{output}");
+        assert!(output.contains("ezstress_work_total{unit=\"iterations\"} 35"), "{output}");
+        assert!(output.contains("ezstress_errors_detected_total 3"), "{output}");
+    }
+}
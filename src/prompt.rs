@@ -1,3 +1,7 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use ezstress::affinity::parse_cpu_list;
+use ezstress::stressors::Stressor;
 use inquire::{CustomUserError};
 use inquire::list_option::ListOption;
 use inquire::ui::{Attributes, Color, RenderConfig, Styled, StyleSheet};
@@ -5,6 +9,15 @@ use inquire::validator::Validation;
 use ocl::{Device, DeviceType, Platform};
 
 
+/// Shows the description next to the name in the stressor menu
+pub struct StressorOption(pub Arc<dyn Stressor>);
+
+impl Display for StressorOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {}", self.0.name(), self.0.description())
+    }
+}
+
 pub fn get_nice_render_config_new() -> RenderConfig {
     let mut render_config = RenderConfig::default();
     render_config.prompt_prefix = Styled::new("❯").with_fg(Color::LightBlue);
@@ -45,6 +58,52 @@ pub fn termination_method_validator(options: &[ListOption<&&str>]) -> Result<Val
     Ok(Validation::Valid)
 }
 
+pub fn cpu_list_validator(option: &str) -> Result<Validation, CustomUserError> {
+    match parse_cpu_list(option) {
+        Ok(cpus) if cpus.is_empty() => Ok(Validation::Invalid("At least one CPU is required".into())),
+        Ok(_) => Ok(Validation::Valid),
+        Err(e) => Ok(Validation::Invalid(e.into())),
+    }
+}
+
+pub fn load_validator(option: &u8) -> Result<Validation, CustomUserError> {
+    match option {
+        0..=100 => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid("The load has to be between 0 and 100 percent".into())),
+    }
+}
+
+/// A constant load of 0 would never run an iteration, only the changing profiles may touch 0
+pub fn constant_load_validator(option: &u8) -> Result<Validation, CustomUserError> {
+    match option {
+        1..=100 => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid("A constant load has to be between 1 and 100 percent".into())),
+    }
+}
+
+pub fn seconds_validator(option: &u16) -> Result<Validation, CustomUserError> {
+    if *option == 0 {
+        return Ok(Validation::Invalid("This cannot be 0 seconds".into()));
+    }
+    Ok(Validation::Valid)
+}
+
+/// The size is asked in MB, it has to fit into a u64 once converted to bytes
+pub fn file_size_validator(option: &u64) -> Result<Validation, CustomUserError> {
+    match option.checked_mul(1024 * 1024) {
+        Some(0) => Ok(Validation::Invalid("The file cannot be 0 MB".into())),
+        Some(_) => Ok(Validation::Valid),
+        None => Ok(Validation::Invalid("This file is too large".into())),
+    }
+}
+
+pub fn queue_depth_validator(option: &usize) -> Result<Validation, CustomUserError> {
+    if *option == 0 {
+        return Ok(Validation::Invalid("At least one operation has to be in flight".into()));
+    }
+    Ok(Validation::Valid)
+}
+
 pub fn duration_validator(option: &u16)  -> Result<Validation, CustomUserError>  {
     if *option == 0 {
         return Ok(Validation::Invalid("Test cannot be 0 minutes".into()));
@@ -52,6 +111,23 @@ pub fn duration_validator(option: &u16)  -> Result<Validation, CustomUserError>
     Ok(Validation::Valid)
 }
 
+pub fn tolerance_validator(option: &f32) -> Result<Validation, CustomUserError> {
+    if *option <= 0.0 {
+        return Ok(Validation::Invalid("Tolerance must be greater than 0".into()));
+    }
+    Ok(Validation::Valid)
+}
+
+pub fn memory_threshold_validator(option: &u64, total_memory_mb: u64) -> Result<Validation, CustomUserError> {
+    if *option == 0 {
+        return Ok(Validation::Invalid("Threshold must not be 0".into()));
+    }
+    if *option >= total_memory_mb {
+        return Ok(Validation::Invalid(format!("Threshold must be less than your total memory ({total_memory_mb} MB)").into()));
+    }
+    Ok(Validation::Valid)
+}
+
 pub fn platform_formatter(list_option: ListOption<&Platform>) -> String {
     let name = list_option.value.name().unwrap_or("Unknown".to_string());
     let devices = Device::list(list_option.value, Some(DeviceType::GPU))
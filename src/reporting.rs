@@ -1,38 +1,215 @@
+use std::collections::VecDeque;
 use std::io::{stdout, Write};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::thread;
 use std::time::{Duration, Instant};
 use colored::Colorize;
-use sysinfo::System;
+use sysinfo::{System, SystemExt};
+use serde::{Deserialize, Serialize};
+use crate::control::{CancellationToken, StopReason};
+use crate::events::{Event, EventBus, Sample, Stage, WorkerProgress};
 use crate::sensors;
 
 pub const CARRIAGE_RETURN: char = '\r';
+// the steady state window only needs a coarse history, not every loop iteration
+const STEADY_STATE_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const BASELINE_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+// the monitor must not spin, its own load would skew the measured cpu usage
+const MONITOR_INTERVAL: Duration = Duration::from_millis(50);
+// how much the cpu has to heat up before a fan that hasn't reacted is considered broken
+const FAN_CHECK_TEMP_RISE: f32 = 10.0;
+// rpm a fan has to gain over its first reading to count as spinning up
+const FAN_SPIN_UP_MARGIN: u32 = 100;
+// a fan that falls below this share of the fastest it spun during the run is considered stalled
+const FAN_STALL_FRACTION: f32 = 0.5;
 
 pub struct BackgroundReport
 {
     pub average_cpu_temp: Option<f32>,
     pub min_cpu_temp: Option<f32>,
     pub max_cpu_temp: Option<f32>,
+    pub fan_stats: Vec<FanStats>,
+    pub warnings: Vec<String>,
+    pub steady_state: Option<SteadyState>,
+    pub memory_stats: MemoryStats,
+    pub average_cpu_usage: Option<f32>,
 }
 
-pub fn watch_in_background(
-    stop_temperature: Option<u8>,
-    duration: Option<Duration>,
+/// All values are in bytes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MemoryStats {
+    pub total_memory: u64,
+    pub peak_used_memory: u64,
+    pub min_available_memory: u64,
+    pub total_swap: u64,
+    pub peak_used_swap: u64,
+}
+
+impl MemoryStats {
+    fn new(system: &System) -> Self {
+        MemoryStats {
+            total_memory: system.total_memory(),
+            peak_used_memory: system.used_memory(),
+            min_available_memory: system.available_memory(),
+            total_swap: system.total_swap(),
+            peak_used_swap: system.used_swap(),
+        }
+    }
+
+    fn record(&mut self, system: &System) {
+        self.peak_used_memory = self.peak_used_memory.max(system.used_memory());
+        self.min_available_memory = self.min_available_memory.min(system.available_memory());
+        self.peak_used_swap = self.peak_used_swap.max(system.used_swap());
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct StabilizationCriteria {
+    /// The temperature has to stay within this many degrees Celsius...
+    pub tolerance: f32,
+    /// ...for at least this long
+    pub window: Duration,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SteadyState {
+    pub temperature: f32,
+    pub time_to_steady_state: Duration,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TerminationConditions {
+    pub duration: Option<Duration>,
+    pub stop_temperature: Option<u8>,
+    pub stop_on_fan_failure: bool,
+    pub stabilization: Option<StabilizationCriteria>,
+    /// In bytes
+    pub min_available_memory: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FanStats {
+    pub label: String,
+    /// The first reading, before the load heated anything up
+    #[serde(default)]
+    pub initial_rpm: u32,
+    /// The latest reading
+    #[serde(default)]
+    pub last_rpm: u32,
+    pub min_rpm: u32,
+    pub max_rpm: u32,
+    total_rpm: u64,
+    samples: u64,
+}
+
+impl FanStats {
+    fn new(reading: &sensors::FanReading) -> Self {
+        FanStats {
+            label: reading.label.clone(),
+            initial_rpm: reading.rpm,
+            last_rpm: reading.rpm,
+            min_rpm: reading.rpm,
+            max_rpm: reading.rpm,
+            total_rpm: 0,
+            samples: 0,
+        }
+    }
+
+    fn record(&mut self, rpm: u32) {
+        self.last_rpm = rpm;
+        self.min_rpm = self.min_rpm.min(rpm);
+        self.max_rpm = self.max_rpm.max(rpm);
+        self.total_rpm += rpm as u64;
+        self.samples += 1;
+    }
+
+    pub fn average_rpm(&self) -> f32 {
+        if self.samples == 0 { 0.0 } else { self.total_rpm as f32 / self.samples as f32 }
+    }
+
+    /// Only meaningful once the temperature has climbed, a fan idling at a fixed speed on a cold
+    /// machine is perfectly healthy. Under load a fan fails when it stays at 0 RPM, never speeds
+    /// up, or stops or slows down after it had spun up.
+    pub fn failure(&self) -> Option<&'static str> {
+        if self.max_rpm == 0 {
+            Some("stayed at 0 RPM")
+        } else if self.last_rpm == 0 {
+            Some("stopped spinning")
+        } else if (self.last_rpm as f32) < self.max_rpm as f32 * FAN_STALL_FRACTION {
+            Some("slowed to less than half its peak speed")
+        } else if self.max_rpm < self.initial_rpm + FAN_SPIN_UP_MARGIN {
+            Some("never spun up")
+        } else {
+            None
+        }
+    }
+}
+
+/// A warning for every failed fan, none unless the cpu heated up enough for the fans to react
+fn fan_warnings(fan_stats: &[FanStats], first_cpu_temp: Option<f32>, max_cpu_temp: f32) -> Vec<String> {
+    let temperature_climbed = first_cpu_temp
+        .is_some_and(|first_temp| max_cpu_temp - first_temp >= FAN_CHECK_TEMP_RISE);
+    match temperature_climbed {
+        true => fan_stats
+            .iter()
+            .filter_map(|stats| stats.failure().map(|failure| format!("Fan {} {failure} while the temperature climbed", stats.label)))
+            .collect(),
+        false => vec![],
+    }
+}
+
+fn record_fans(fan_stats: &mut Vec<FanStats>) {
+    for reading in sensors::fan_speeds() {
+        match fan_stats.iter_mut().find(|stats| stats.label == reading.label) {
+            Some(stats) => stats.record(reading.rpm),
+            None => {
+                let mut stats = FanStats::new(&reading);
+                stats.record(reading.rpm);
+                fan_stats.push(stats);
+            }
+        }
+    }
+}
+
+pub(crate) fn watch_in_background(
+    conditions: &TerminationConditions,
     system: &mut System,
     start_time: Instant,
-    running: Arc<AtomicUsize>,
+    token: &CancellationToken,
+    workers: &[WorkerCounters],
+    events: &EventBus,
 ) -> BackgroundReport {
+    let TerminationConditions { duration, stop_temperature, stop_on_fan_failure, stabilization, min_available_memory } = *conditions;
     let mut iterations = 0;
     let mut average_cpu_temp = 0f32;
     let mut min_cpu_temp = 999.9f32;
     let mut max_cpu_temp = 0f32;
+    let mut first_cpu_temp: Option<f32> = None;
+    let mut fan_stats: Vec<FanStats> = Vec::new();
+    let mut temp_window: VecDeque<(Instant, f32)> = VecDeque::new();
+    let mut steady_state: Option<SteadyState> = None;
+    system.refresh_memory();
+    let mut memory_stats = MemoryStats::new(system);
+    let mut sampler = Sampler::new(duration);
 
 
-    while running.load(Ordering::SeqCst) == 0 {
+    while !token.is_stopped() {
 
         let temp = sensors::cpu_temp(system, true);
+        record_fans(&mut fan_stats);
+        system.refresh_memory();
+        memory_stats.record(system);
+
+        if let Some(min_available) = min_available_memory {
+            if system.available_memory() < min_available {
+                token.stop(StopReason::LowMemory);
+            }
+        }
 
         if let Some(temp) = temp {
+            let first_temp = *first_cpu_temp.get_or_insert(temp);
 
             if temp > max_cpu_temp {
                 max_cpu_temp = temp;
@@ -47,41 +224,321 @@ pub fn watch_in_background(
 
             if let Some(stop_temp) = stop_temperature {
                 if temp > stop_temp as f32 {
-                    running.store(2, Ordering::SeqCst)
+                    token.stop(StopReason::Temperature);
+                }
+            }
+
+            if stop_on_fan_failure
+                && temp - first_temp >= FAN_CHECK_TEMP_RISE
+                && fan_stats.iter().any(|stats| stats.failure().is_some()) {
+                token.stop(StopReason::FanFailure);
+            }
+
+            if let Some(criteria) = stabilization {
+                let sampled_recently = temp_window
+                    .back()
+                    .is_some_and(|(sampled_at, _)| sampled_at.elapsed() < STEADY_STATE_SAMPLE_INTERVAL);
+
+                if !sampled_recently {
+                    steady_state = record_steady_state_sample(&mut temp_window, criteria, temp, token.active_time(start_time));
+                    if steady_state.is_some() {
+                        token.stop(StopReason::SteadyState);
+                    }
                 }
             }
         }
 
 
         if let Some(duration) = duration {
-            if start_time.elapsed() > duration {
-                running.store(1, Ordering::SeqCst)
+            if token.active_time(start_time) > duration {
+                token.stop(StopReason::TimeLimit);
             }
         }
 
-        print!("{} ", prettify_output(duration, start_time, temp));
-        let _ = stdout().flush();
+        if sampler.due() {
+            let progress = workers.iter().map(WorkerCounters::progress).collect();
+            sampler.sample(events, token.active_time(start_time), system, temp, progress);
+        }
         iterations += 1;
+        thread::sleep(MONITOR_INTERVAL);
     }
 
+    let warnings = fan_warnings(&fan_stats, first_cpu_temp, max_cpu_temp);
+
     BackgroundReport {
         average_cpu_temp: if average_cpu_temp == 0.0 { None } else { Some(average_cpu_temp / iterations as f32) },
         min_cpu_temp: if min_cpu_temp == 999.9 { None } else { Some(min_cpu_temp) },
         max_cpu_temp: if max_cpu_temp == 0.0  { None } else { Some(max_cpu_temp) },
+        fan_stats,
+        warnings,
+        steady_state,
+        memory_stats,
+        average_cpu_usage: sampler.average_usage(),
     }
 }
 
-pub fn prettify_output(
+/// Pushes `temp` into the sliding window and drops samples that fell out of it. Once the
+/// window covers the full `criteria.window` and every sample is within `criteria.tolerance`
+/// of each other the temperature is considered stable.
+fn record_steady_state_sample(
+    window: &mut VecDeque<(Instant, f32)>,
+    criteria: StabilizationCriteria,
+    temp: f32,
+    elapsed: Duration,
+) -> Option<SteadyState> {
+    let now = Instant::now();
+    window.push_back((now, temp));
+
+    // keep the newest sample that is at least `criteria.window` old so the window stays full
+    while window.len() > 1 && now.duration_since(window[1].0) >= criteria.window {
+        window.pop_front();
+    }
+
+    let (oldest, _) = *window.front()?;
+    if now.duration_since(oldest) < criteria.window {
+        return None
+    }
+
+    let min = window.iter().map(|(_, temp)| *temp).fold(f32::MAX, f32::min);
+    let max = window.iter().map(|(_, temp)| *temp).fold(f32::MIN, f32::max);
+    if max - min >= criteria.tolerance {
+        return None
+    }
+
+    let average = window.iter().map(|(_, temp)| *temp).sum::<f32>() / window.len() as f32;
+    Some(SteadyState {
+        temperature: average,
+        time_to_steady_state: elapsed,
+    })
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct BaselineSettings {
+    pub idle_duration: Duration,
+    /// Cooldown is finished once the temperature is back within this many degrees of idle
+    pub cooldown_tolerance: f32,
+    pub cooldown_timeout: Duration,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThermalProfile {
+    pub idle_temp: f32,
+    pub peak_delta_over_idle: Option<f32>,
+    pub average_delta_over_idle: Option<f32>,
+    pub cooldown_tolerance: f32,
+    /// None when the temperature did not return to idle before the cooldown timed out
+    pub time_to_idle: Option<Duration>,
+}
+
+/// Samples the cpu temperature with no load applied and returns the average.
+pub fn measure_idle_baseline(
+    system: &mut System,
+    idle_duration: Duration,
+    token: &CancellationToken,
+    events: &EventBus,
+) -> Option<f32> {
+    let start_time = Instant::now();
+    let mut sampler = Sampler::new(Some(idle_duration));
+    let mut total_temp = 0f32;
+    let mut samples = 0;
+
+    while start_time.elapsed() < idle_duration && !token.is_stopped() {
+        if let Some(temp) = sensors::cpu_temp(system, true) {
+            total_temp += temp;
+            samples += 1;
+            sampler.sample(events, start_time.elapsed(), system, Some(temp), vec![]);
+        }
+        thread::sleep(BASELINE_SAMPLE_INTERVAL);
+    }
+
+    match samples {
+        0 => None,
+        samples => Some(total_temp / samples as f32),
+    }
+}
+
+/// Samples the sensors with no load applied until `token` is stopped or `duration` has passed,
+/// this keeps front-ends such as the metrics exporter fed while no run is going.
+pub fn watch_idle(
+    system: &mut System,
     duration: Option<Duration>,
-    start_time: Instant,
+    token: &CancellationToken,
+    events: &EventBus,
+) {
+    let start_time = Instant::now();
+    let mut sampler = Sampler::new(None);
+
+    while !token.is_stopped() && duration.is_none_or(|duration| start_time.elapsed() < duration) {
+        let temp = sensors::cpu_temp(system, true);
+        sampler.sample(events, start_time.elapsed(), system, temp, vec![]);
+        thread::sleep(SAMPLE_INTERVAL);
+    }
+}
+
+/// Waits for the cpu to get back within `tolerance` of `idle_temp` and returns how long it took.
+/// None when it timed out or `token` was cancelled.
+pub fn watch_cooldown(
+    system: &mut System,
+    idle_temp: f32,
+    tolerance: f32,
+    timeout: Duration,
+    token: &CancellationToken,
+    events: &EventBus,
+) -> Option<Duration> {
+    let start_time = Instant::now();
+    let mut sampler = Sampler::new(None);
+
+    loop {
+        if start_time.elapsed() > timeout || token.is_cancelled() {
+            break None
+        }
+
+        let temp = sensors::cpu_temp(system, true)?;
+        if temp <= idle_temp + tolerance {
+            break Some(start_time.elapsed())
+        }
+
+        sampler.sample(events, start_time.elapsed(), system, Some(temp), vec![]);
+        thread::sleep(BASELINE_SAMPLE_INTERVAL);
+    }
+}
+
+/// Live counters of a single worker thread, so the monitor can report progress while it runs
+#[derive(Default)]
+pub(crate) struct WorkerCounters {
+    pub(crate) iterations: AtomicU64,
+    pub(crate) work_done: AtomicU64,
+    pub(crate) errors: AtomicU64,
+}
+
+impl WorkerCounters {
+    pub(crate) fn progress(&self) -> WorkerProgress {
+        WorkerProgress {
+            iterations: self.iterations.load(Ordering::Relaxed),
+            work_done: self.work_done.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Turns the monitor loops' readings into [`Event::Sample`]s, at most one every [`SAMPLE_INTERVAL`]
+pub(crate) struct Sampler {
+    duration: Option<Duration>,
+    last_sample: Option<(Instant, u64)>,
+    /// Sum and count of the whole-system cpu usage readings
+    usage: (f32, u32),
+}
+
+impl Sampler {
+    pub(crate) fn new(duration: Option<Duration>) -> Self {
+        Sampler { duration, last_sample: None, usage: (0.0, 0) }
+    }
+
+    /// Average usage over every logical cpu and sample, in percent
+    pub(crate) fn average_usage(&self) -> Option<f32> {
+        let (total, count) = self.usage;
+        (count > 0).then(|| total / count as f32)
+    }
+
+    pub(crate) fn due(&self) -> bool {
+        self.last_sample.is_none_or(|(sampled_at, _)| sampled_at.elapsed() >= SAMPLE_INTERVAL)
+    }
+
+    /// `system` should have its components refreshed already, as the monitor loops do when
+    /// reading the cpu temperature
+    pub(crate) fn sample(&mut self, events: &EventBus, elapsed: Duration, system: &mut System, cpu_temp: Option<f32>, workers: Vec<WorkerProgress>) {
+        let work_done = workers.iter().map(|worker| worker.work_done).sum();
+        let now = Instant::now();
+        let cores = sensors::core_loads(system);
+        // the first reading has nothing to measure the usage against and is always 0
+        if self.last_sample.is_some() && !cores.is_empty() {
+            self.usage.0 += cores.iter().map(|core| core.usage).sum::<f32>() / cores.len() as f32;
+            self.usage.1 += 1;
+        }
+        let throughput = match self.last_sample {
+            Some((sampled_at, last_work)) => (work_done - last_work) as f64 / now.duration_since(sampled_at).as_secs_f64(),
+            None => 0.0,
+        };
+        self.last_sample = Some((now, work_done));
+
+        events.emit(Event::Sample(Sample {
+            elapsed,
+            remaining: self.duration.map(|duration| duration.saturating_sub(elapsed)),
+            cpu_temp,
+            temperatures: sensors::all_temps(system, false),
+            cores,
+            work_done,
+            workers,
+            throughput,
+        }));
+    }
+}
+
+/// Prints a run's events to the terminal, this is what the CLI shows while a test runs.
+pub struct TerminalPrinter {
+    live_status: bool,
+}
+
+impl TerminalPrinter {
+    pub fn new(live_status: bool) -> Self {
+        TerminalPrinter { live_status }
+    }
+
+    /// Blocks until the run finished or the session was dropped
+    pub fn consume(&self, events: Receiver<Event>) {
+        let mut stage = Stage::Load;
+        let mut status_printed = false;
+
+        for event in events {
+            // the status line is overwritten in place, anything else has to start on a new line
+            if status_printed && !matches!(event, Event::Sample(_)) {
+                println!();
+                status_printed = false;
+            }
+
+            match event {
+                Event::StageChanged(new_stage) => {
+                    stage = new_stage;
+                    match stage {
+                        Stage::IdleBaseline => println!("{}", "💤 Measuring idle baseline, let your system sit idle...".white().bold()),
+                        Stage::Prepare => println!("{}", "🗂 Preparing the workers, the load starts once they are ready...".white().bold()),
+                        Stage::Cooldown => println!("{}", "🧊 Workers stopped, watching the cooldown...".white().bold()),
+                        Stage::Load => {}
+                    }
+                }
+                Event::Started { stressor, .. } => {
+                    println!("{}", format!("🏁 Starting {stressor}. If you wish to stop the test at any point press Control+C").white().bold());
+                }
+                Event::Sample(sample) if self.live_status => {
+                    let suffix = match stage {
+                        Stage::IdleBaseline => " (idle baseline)",
+                        Stage::Cooldown => " (cooling down)",
+                        Stage::Prepare | Stage::Load => "",
+                    };
+                    print!("{}{suffix} ", prettify_output(sample.elapsed, sample.remaining, sample.cpu_temp));
+                    let _ = stdout().flush();
+                    status_printed = true;
+                }
+                Event::Sample(_) => {}
+                Event::Warning(warning) => println!("{}", format!("⚠ {warning}").yellow()),
+                Event::Error(error) => println!("{}", error.red()),
+                Event::Finished(_) => break,
+            }
+        }
+    }
+}
+
+pub fn prettify_output(
+    elapsed: Duration,
+    remaining: Option<Duration>,
     current_temp: Option<f32>
 ) -> String {
     let mut display_string = String::new();
     display_string.push(CARRIAGE_RETURN);
 
-    let time_left = match duration {
-        Some(duration) => duration.as_secs() - start_time.elapsed().as_secs(),
-        None => start_time.elapsed().as_secs(),
+    let time_left = match remaining {
+        Some(remaining) => remaining.as_secs(),
+        None => elapsed.as_secs(),
     };
 
     let time_string = match time_left  {
@@ -113,4 +570,52 @@ pub fn prettify_output(
 
     display_string
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fan(readings: &[u32]) -> FanStats {
+        let mut stats = FanStats::new(&sensors::FanReading { label: String::from("fan1"), rpm: readings[0] });
+        for rpm in readings {
+            stats.record(*rpm);
+        }
+        stats
+    }
+
+    #[test]
+    fn dead_fan_fails_while_the_temperature_rises() {
+        let dead = fan(&[0, 0, 0]);
+        assert_eq!(dead.failure(), Some("stayed at 0 RPM"));
+        assert_eq!(fan_warnings(&[dead], Some(45.0), 80.0), ["Fan fan1 stayed at 0 RPM while the temperature climbed"]);
+    }
+
+    #[test]
+    fn fan_that_never_ramps_fails_while_the_temperature_rises() {
+        let stuck = fan(&[1200, 1250, 1210]);
+        assert_eq!(stuck.failure(), Some("never spun up"));
+        assert_eq!(fan_warnings(&[stuck], Some(45.0), 80.0), ["Fan fan1 never spun up while the temperature climbed"]);
+    }
+
+    #[test]
+    fn fans_are_not_judged_on_a_cool_run() {
+        assert!(fan_warnings(&[fan(&[0, 0, 0]), fan(&[1200, 1200])], Some(45.0), 50.0).is_empty());
+        assert!(fan_warnings(&[fan(&[0, 0, 0])], None, 0.0).is_empty());
+    }
+
+    #[test]
+    fn fan_that_spins_up_is_healthy() {
+        assert_eq!(fan(&[800, 1500, 1450]).failure(), None);
+    }
+
+    #[test]
+    fn fan_that_stops_fails() {
+        assert_eq!(fan(&[800, 1500, 0]).failure(), Some("stopped spinning"));
+    }
+
+    #[test]
+    fn fan_that_slows_below_half_its_peak_fails() {
+        assert_eq!(fan(&[800, 2000, 999]).failure(), Some("slowed to less than half its peak speed"));
+        assert_eq!(fan(&[800, 2000, 1000]).failure(), None);
+    }
+}
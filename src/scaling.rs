@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::job::Job;
+use crate::session::{Component, StressSession};
+use crate::stressors::Stressor;
+
+pub const DEFAULT_SCALING_DURATION: Duration = Duration::from_secs(15);
+
+/// Thread counts doubling from 1, `max_threads` is always the last step even when it is not a
+/// power of two
+pub fn scaling_steps(max_threads: usize) -> Vec<usize> {
+    let mut steps: Vec<usize> = (0..usize::BITS)
+        .map(|exponent| 1 << exponent)
+        .take_while(|threads| *threads < max_threads)
+        .collect();
+    steps.push(max_threads.max(1));
+    steps
+}
+
+/// Runs a stressor at every step of [`scaling_steps`] to show where more threads stop helping,
+/// e.g. once the SMT siblings or the memory bandwidth are saturated
+pub struct ScalingTest {
+    pub stressor: Arc<dyn Stressor>,
+    /// How long every thread count runs
+    pub duration: Duration,
+    pub thermal_ceiling: f32,
+}
+
+impl ScalingTest {
+    pub fn session(&self, threads: usize) -> Result<StressSession, String> {
+        StressSession::builder()
+            .component(Component::Cpu)
+            .stressor(self.stressor.clone())
+            .threads(threads)
+            .duration(self.duration)
+            .thermal_ceiling(self.thermal_ceiling)
+            .build()
+    }
+}
+
+pub struct ScalingStep {
+    pub threads: usize,
+    pub throughput: f64,
+    /// Throughput relative to one thread
+    pub speedup: f64,
+    /// Speedup per thread in percent, 100 means perfect scaling
+    pub efficiency: f64,
+    /// What every thread added since the previous step contributed, relative to one thread, in
+    /// percent. This is where SMT siblings and memory bound stressors fall off.
+    pub marginal_gain: Option<f64>,
+    pub max_cpu_temp: Option<f32>,
+}
+
+pub struct ScalingReport {
+    pub stressor: String,
+    pub unit: String,
+    pub steps: Vec<ScalingStep>,
+}
+
+impl ScalingReport {
+    /// `runs` are the thread counts with their finished jobs, the first one is the baseline
+    pub fn new(runs: &[(usize, Job)]) -> Result<ScalingReport, String> {
+        let (single_threads, single_job) = runs.first().ok_or("The scaling test has no finished steps")?;
+        let single = single_job.throughput() / *single_threads as f64;
+
+        let mut steps: Vec<ScalingStep> = Vec::with_capacity(runs.len());
+        for (threads, job) in runs {
+            let throughput = job.throughput();
+            let speedup = match single {
+                single if single > 0.0 => throughput / single,
+                _ => 0.0,
+            };
+            let marginal_gain = steps.last()
+                .filter(|previous| previous.threads < *threads && single > 0.0)
+                .map(|previous| (throughput - previous.throughput) / (threads - previous.threads) as f64 / single * 100.0);
+
+            steps.push(ScalingStep {
+                threads: *threads,
+                throughput,
+                speedup,
+                efficiency: speedup / *threads as f64 * 100.0,
+                marginal_gain,
+                max_cpu_temp: job.max_cpu_temp,
+            });
+        }
+
+        Ok(ScalingReport {
+            stressor: single_job.name.clone(),
+            unit: single_job.unit.clone(),
+            steps,
+        })
+    }
+
+    /// One row per step, empty fields where a value is unknown
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("threads,throughput_{}_per_second,speedup,efficiency_percent,marginal_gain_percent,max_cpu_temp_celsius\n", self.unit.replace(' ', "_"));
+        for step in &self.steps {
+            let marginal_gain = step.marginal_gain.map_or_else(String::new, |gain| format!("{gain:.2}"));
+            let max_temp = step.max_cpu_temp.map_or_else(String::new, |temp| format!("{temp:.1}"));
+            csv.push_str(&format!("{},{:.2},{:.3},{:.2},{marginal_gain},{max_temp}\n",
+                                  step.threads, step.throughput, step.speedup, step.efficiency));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control::StopReason;
+    use super::*;
+
+    fn job(work_done: u64, threads: usize) -> Job {
+        Job {
+            name: String::from("Primes"),
+            total_iterations: work_done,
+            work_done,
+            unit: String::from("prime numbers"),
+            errors_detected: None,
+            cpu_count: Some(threads),
+            core_mapping: vec![],
+            unpinned_cpus: vec![],
+            elapsed: Duration::from_secs(10),
+            average_cpu_temp: None,
+            min_cpu_temp: None,
+            max_cpu_temp: Some(60.0 + threads as f32),
+            average_power: None,
+            average_cpu_usage: None,
+            fan_stats: vec![],
+            warnings: vec![],
+            steady_state: None,
+            memory_stats: None,
+            details: vec![],
+            thermal_profile: None,
+            stop_reason: StopReason::TimeLimit,
+            thermal_trip: None,
+        }
+    }
+
+    #[test]
+    fn steps_double_and_end_at_the_maximum() {
+        assert_eq!(scaling_steps(0), [1]);
+        assert_eq!(scaling_steps(1), [1]);
+        assert_eq!(scaling_steps(2), [1, 2]);
+        assert_eq!(scaling_steps(8), [1, 2, 4, 8]);
+        assert_eq!(scaling_steps(6), [1, 2, 4, 6]);
+        assert_eq!(scaling_steps(12), [1, 2, 4, 8, 12]);
+        assert_eq!(scaling_steps(17), [1, 2, 4, 8, 16, 17]);
+    }
+
+    #[test]
+    fn report_compares_every_step_to_one_thread() {
+        let runs = [(1, job(100, 1)), (2, job(200, 2)), (4, job(300, 4)), (6, job(300, 6))];
+        let report = ScalingReport::new(&runs).unwrap();
+        assert_eq!(report.stressor, "Primes");
+
+        let step = |threads: usize| report.steps.iter().find(|step| step.threads == threads).unwrap();
+        assert_eq!(step(1).throughput, 10.0);
+        assert_eq!(step(1).speedup, 1.0);
+        assert_eq!(step(1).efficiency, 100.0);
+        assert_eq!(step(1).marginal_gain, None);
+
+        assert_eq!(step(2).speedup, 2.0);
+        assert_eq!(step(2).efficiency, 100.0);
+        assert_eq!(step(2).marginal_gain, Some(100.0));
+
+        // two more threads added one thread's worth of work
+        assert_eq!(step(4).speedup, 3.0);
+        assert_eq!(step(4).efficiency, 75.0);
+        assert_eq!(step(4).marginal_gain, Some(50.0));
+
+        assert_eq!(step(6).efficiency, 50.0);
+        assert_eq!(step(6).marginal_gain, Some(0.0));
+    }
+
+    #[test]
+    fn report_csv_has_a_row_per_step() {
+        let report = ScalingReport::new(&[(1, job(100, 1)), (3, job(250, 3))]).unwrap();
+        assert_eq!(report.to_csv(), "threads,throughput_prime_numbers_per_second,speedup,efficiency_percent,marginal_gain_percent,max_cpu_temp_celsius\n\
+                                     1,10.00,1.000,100.00,,61.0\n\
+                                     3,25.00,2.500,83.33,75.00,63.0\n");
+        assert!(ScalingReport::new(&[]).is_err());
+    }
+}
@@ -1,4 +1,13 @@
-use sysinfo::{ComponentExt, System, SystemExt};
+use sysinfo::{ComponentExt, CpuExt, System, SystemExt};
+use serde::Serialize;
+
+#[derive(Clone, Serialize)]
+pub struct SensorReading {
+    pub label: String,
+    pub temperature: f32,
+    // hwmon temp*_crit on linux, not every sensor exposes one
+    pub critical: Option<f32>,
+}
 
 pub fn cpu_temp(
     system: &mut System,
@@ -16,4 +25,158 @@ pub fn cpu_temp(
        .iter()
        .find(|c| c.label().contains("CPU"))
        .map(|component| component.temperature())
-}
\ No newline at end of file
+}
+
+pub fn all_temps(
+    system: &mut System,
+    refresh: bool
+) -> Vec<SensorReading> {
+    if cfg!(all(target_arch = "aarch64", target_os = "macos")) {
+        return vec![]
+    }
+
+    if refresh {
+        system.refresh_components_list();
+    }
+
+    system.components()
+        .iter()
+        .map(|component| SensorReading {
+            label: component.label().to_string(),
+            temperature: component.temperature(),
+            critical: component.critical().filter(|crit| *crit > 0.0),
+        })
+        .collect()
+}
+
+#[derive(Clone, Serialize)]
+pub struct CoreLoad {
+    /// In percent
+    pub usage: f32,
+    /// In MHz
+    pub frequency: u64,
+}
+
+/// Usage is measured since the previous call, so the first call on a fresh [`System`] reads 0%
+pub fn core_loads(system: &mut System) -> Vec<CoreLoad> {
+    system.refresh_cpu();
+    system.cpus()
+        .iter()
+        .map(|cpu| CoreLoad { usage: cpu.cpu_usage(), frequency: cpu.frequency() })
+        .collect()
+}
+
+/// The highest frequency any core is rated for in MHz, boost included. None when the OS does
+/// not tell, e.g. in most virtual machines.
+#[cfg(target_os = "linux")]
+pub fn max_core_frequency() -> Option<u64> {
+    use std::fs;
+
+    fs::read_dir("/sys/devices/system/cpu").ok()?
+        .flatten()
+        .filter_map(|cpu| fs::read_to_string(cpu.path().join("cpufreq/cpuinfo_max_freq")).ok())
+        .filter_map(|khz| khz.trim().parse::<u64>().ok())
+        .max()
+        .map(|khz| khz / 1000)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn max_core_frequency() -> Option<u64> {
+    None
+}
+
+pub struct FanReading {
+    pub label: String,
+    pub rpm: u32,
+}
+
+#[cfg(target_os = "linux")]
+pub fn fan_speeds() -> Vec<FanReading> {
+    use std::fs;
+
+    let Ok(hwmons) = fs::read_dir("/sys/class/hwmon") else {
+        return vec![]
+    };
+
+    let mut fans = Vec::new();
+    for hwmon in hwmons.flatten() {
+        let path = hwmon.path();
+        let chip = fs::read_to_string(path.join("name"))
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|_| hwmon.file_name().to_string_lossy().to_string());
+
+        let Ok(entries) = fs::read_dir(&path) else {
+            continue
+        };
+
+        let mut inputs = entries
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("fan") && name.ends_with("_input"))
+            .collect::<Vec<String>>();
+        inputs.sort();
+
+        for input in inputs {
+            let Some(rpm) = fs::read_to_string(path.join(&input))
+                .ok()
+                .and_then(|rpm| rpm.trim().parse::<u32>().ok()) else {
+                continue
+            };
+
+            let fan = input.trim_end_matches("_input");
+            let label = fs::read_to_string(path.join(format!("{fan}_label")))
+                .map(|label| format!("{chip} {}", label.trim()))
+                .unwrap_or_else(|_| format!("{chip} {fan}"));
+
+            fans.push(FanReading { label, rpm });
+        }
+    }
+    fans
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn fan_speeds() -> Vec<FanReading> {
+    vec![]
+}
+
+/// Average CPU package power over a span of time, from the RAPL energy counters. Most systems
+/// only let root read them, so expect None when running unprivileged or on non-Intel/AMD machines.
+pub struct EnergyMeter {
+    started_at: std::time::Instant,
+    start_energy: u64,
+    max_energy: u64,
+}
+
+const RAPL_PACKAGE: &str = "/sys/class/powercap/intel-rapl:0";
+
+impl EnergyMeter {
+    #[cfg(target_os = "linux")]
+    pub fn start() -> Option<EnergyMeter> {
+        let max_energy = read_rapl("max_energy_range_uj")?;
+        let start_energy = read_rapl("energy_uj")?;
+        Some(EnergyMeter { started_at: std::time::Instant::now(), start_energy, max_energy })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn start() -> Option<EnergyMeter> {
+        None
+    }
+
+    /// In watts
+    pub fn average_power(&self) -> Option<f32> {
+        let energy = read_rapl("energy_uj")?;
+        // the counter wraps around at max_energy
+        let used = match energy >= self.start_energy {
+            true => energy - self.start_energy,
+            false => self.max_energy - self.start_energy + energy,
+        };
+        let seconds = self.started_at.elapsed().as_secs_f64();
+        (seconds > 0.0).then(|| (used as f64 / 1_000_000.0 / seconds) as f32)
+    }
+}
+
+fn read_rapl(file: &str) -> Option<u64> {
+    std::fs::read_to_string(format!("{RAPL_PACKAGE}/{file}"))
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
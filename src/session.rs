@@ -0,0 +1,643 @@
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use ocl::Device;
+use serde::{Deserialize, Serialize};
+use sysinfo::{System, SystemExt};
+use crate::affinity::{self, CpuTopology, PinningStrategy};
+use crate::control::{CancellationToken, StopReason};
+use crate::events::{Event, EventBus, Stage, WorkerProgress};
+use crate::job::Job;
+use crate::load::{DutyCycle, LoadProfile, TargetLevel, drive_load};
+use crate::reporting::{BaselineSettings, Sampler, StabilizationCriteria, TerminationConditions, ThermalProfile, WorkerCounters, measure_idle_baseline, watch_cooldown, watch_in_background};
+use crate::sensors::{self, EnergyMeter};
+use crate::stressors::{OpenCLContext, OpenCLProgram, StressWorker, Stressor};
+use crate::watchdog::{DEFAULT_THERMAL_CEILING, watch_thermals};
+
+// how far the measured cpu usage may be off a target load before the run warns about it, in percent
+const LOAD_TOLERANCE: f32 = 10.0;
+// most workers are ready at once, the preparation stage is only announced when it takes longer
+const PREPARE_NOTICE_DELAY: Duration = Duration::from_millis(500);
+
+pub enum Component {
+    Cpu,
+    Gpu(Device),
+}
+
+/// A fully configured stress run. Use [`StressSession::builder`] to create one and
+/// [`StressSession::run`] to execute it, the session can be run as many times as needed.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use ezstress::{Component, StressSession};
+/// use ezstress::stressors::StressorRegistry;
+///
+/// let fibonacci = StressorRegistry::with_builtins().find("Fibonacci").unwrap();
+/// let job = StressSession::builder()
+///     .component(Component::Cpu)
+///     .stressor(fibonacci)
+///     .threads(4)
+///     .duration(Duration::from_secs(60))
+///     .build()?
+///     .run()?;
+/// println!("{job}");
+/// # Ok::<(), String>(())
+/// ```
+pub struct StressSession {
+    component: Component,
+    stressor: Arc<dyn Stressor>,
+    threads: usize,
+    conditions: TerminationConditions,
+    baseline: Option<BaselineSettings>,
+    thermal_ceiling: f32,
+    verify: bool,
+    pinning: Option<PinningStrategy>,
+    load_profile: Option<LoadProfile>,
+    /// The cpu every worker is pinned to, resolved from `pinning` when the session is built
+    core_mapping: Option<Vec<usize>>,
+    events: EventBus,
+    token: CancellationToken,
+}
+
+/// The settings a session was built with, kept alongside its results in the run history
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunConfig {
+    /// "CPU" or the name of the GPU
+    pub component: String,
+    pub stressor: String,
+    /// The stressor's description, it holds the settings of configurable stressors such as the disk
+    #[serde(default)]
+    pub workload: String,
+    /// None for GPU runs
+    pub threads: Option<usize>,
+    pub conditions: TerminationConditions,
+    pub baseline: Option<BaselineSettings>,
+    pub thermal_ceiling: f32,
+    pub verify: bool,
+    /// None when the OS decides where workers run
+    pub pinning: Option<PinningStrategy>,
+    /// How every worker was throttled, None for full load
+    pub load_profile: Option<LoadProfile>,
+}
+
+pub struct StressSessionBuilder {
+    component: Component,
+    stressor: Option<Arc<dyn Stressor>>,
+    threads: Option<usize>,
+    conditions: TerminationConditions,
+    baseline: Option<BaselineSettings>,
+    thermal_ceiling: f32,
+    verify: bool,
+    pinning: Option<PinningStrategy>,
+    load_profile: Option<LoadProfile>,
+}
+
+impl StressSession {
+    pub fn builder() -> StressSessionBuilder {
+        StressSessionBuilder {
+            component: Component::Cpu,
+            stressor: None,
+            threads: None,
+            conditions: TerminationConditions::default(),
+            baseline: None,
+            thermal_ceiling: DEFAULT_THERMAL_CEILING,
+            verify: false,
+            pinning: None,
+            load_profile: None,
+        }
+    }
+
+    /// Every event of every following [`StressSession::run`] is sent to the returned receiver.
+    /// It disconnects once the session is dropped.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Stops or pauses the current [`StressSession::run`] from anywhere. Every run starts out
+    /// running and unpaused, so cancelling before a run has started has no effect.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn config(&self) -> RunConfig {
+        let (component, threads) = match &self.component {
+            Component::Cpu => (String::from("CPU"), Some(self.threads)),
+            Component::Gpu(device) => (device.name().unwrap_or_else(|_| String::from("GPU")), None),
+        };
+
+        RunConfig {
+            component,
+            stressor: self.stressor.to_string(),
+            workload: self.stressor.description().to_string(),
+            threads,
+            conditions: self.conditions,
+            baseline: self.baseline,
+            thermal_ceiling: self.thermal_ceiling,
+            verify: self.verify,
+            pinning: self.pinning.clone(),
+            load_profile: self.load_profile.clone(),
+        }
+    }
+
+    pub fn run(&self) -> Result<Job, String> {
+        self.token.reset();
+        let mut system = System::new();
+        let result = match &self.component {
+            Component::Cpu => do_cpu_work(self, &mut system),
+            Component::Gpu(device) => OpenCLContext::new(*device)
+                .and_then(|ctx| self.stressor.opencl_program(&ctx))
+                .and_then(|program| do_gpu_work(self, program, &mut system)),
+        };
+
+        match &result {
+            Ok(job) => self.events.emit(Event::Finished(Arc::new(job.clone()))),
+            Err(error) => self.events.emit(Event::Error(error.clone())),
+        }
+        result
+    }
+}
+
+impl StressSessionBuilder {
+    pub fn component(mut self, component: Component) -> Self {
+        self.component = component;
+        self
+    }
+
+    pub fn stressor(mut self, stressor: Arc<dyn Stressor>) -> Self {
+        self.stressor = Some(stressor);
+        self
+    }
+
+    /// Number of worker threads for CPU runs, defaults to every logical core
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Replaces every termination condition at once
+    pub fn termination(mut self, conditions: TerminationConditions) -> Self {
+        self.conditions = conditions;
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.conditions.duration = Some(duration);
+        self
+    }
+
+    pub fn stop_temperature(mut self, celsius: u8) -> Self {
+        self.conditions.stop_temperature = Some(celsius);
+        self
+    }
+
+    pub fn stop_on_fan_failure(mut self, stop: bool) -> Self {
+        self.conditions.stop_on_fan_failure = stop;
+        self
+    }
+
+    pub fn stabilization(mut self, criteria: StabilizationCriteria) -> Self {
+        self.conditions.stabilization = Some(criteria);
+        self
+    }
+
+    /// In bytes
+    pub fn min_available_memory(mut self, bytes: u64) -> Self {
+        self.conditions.min_available_memory = Some(bytes);
+        self
+    }
+
+    pub fn baseline(mut self, baseline: BaselineSettings) -> Self {
+        self.baseline = Some(baseline);
+        self
+    }
+
+    pub fn thermal_ceiling(mut self, celsius: f32) -> Self {
+        self.thermal_ceiling = celsius;
+        self
+    }
+
+    /// Check the output of every iteration and count mismatches, only has an effect for
+    /// stressors that are [`Stressor::verifiable`]
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Pin every worker of a CPU run to a logical cpu, by default the OS schedules them
+    pub fn pinning(mut self, strategy: PinningStrategy) -> Self {
+        self.pinning = Some(strategy);
+        self
+    }
+
+    /// Throttle every worker of a CPU run to this percent of its core by alternating work and
+    /// sleep, e.g. to hold a fan curve at 50% load
+    pub fn target_load(self, percent: u8) -> Self {
+        self.load_profile(LoadProfile::Constant(percent))
+    }
+
+    /// Vary the load of every worker of a CPU run over time, see [`LoadProfile`]
+    pub fn load_profile(mut self, profile: LoadProfile) -> Self {
+        self.load_profile = Some(profile);
+        self
+    }
+
+    pub fn build(self) -> Result<StressSession, String> {
+        let stressor = self.stressor.ok_or("No stressor was chosen")?;
+        let conditions = self.conditions;
+        let verifiable = stressor.verifiable();
+
+        let threads = match self.threads {
+            Some(0) => return Err("At least one thread is required".to_string()),
+            Some(threads) => threads,
+            None => thread::available_parallelism().map_or(1, |threads| threads.get()),
+        };
+
+        if conditions.duration == Some(Duration::ZERO) {
+            return Err("The time limit has to be longer than 0 seconds".to_string())
+        }
+
+        let has_cpu_only_conditions = conditions.stop_temperature.is_some()
+            || conditions.stop_on_fan_failure
+            || conditions.stabilization.is_some()
+            || conditions.min_available_memory.is_some()
+            || self.baseline.is_some();
+
+        match self.component {
+            Component::Cpu if !stressor.runs_on_cpu() => {
+                return Err(format!("{stressor} does not run on the CPU"))
+            }
+            Component::Gpu(_) if !stressor.runs_on_gpu() => {
+                return Err(format!("{stressor} does not run on the GPU"))
+            }
+            Component::Cpu if conditions.duration.is_none() && !has_cpu_only_conditions => {
+                return Err("At least one termination condition is required".to_string())
+            }
+            Component::Gpu(_) if conditions.duration.is_none() => {
+                return Err("GPU tests can only be terminated by time, a duration is required".to_string())
+            }
+            Component::Gpu(_) if has_cpu_only_conditions => {
+                return Err("GPU tests only support time based termination".to_string())
+            }
+            _ => {}
+        }
+
+        match (&self.load_profile, &self.component) {
+            (Some(_), Component::Gpu(_)) => return Err("Load profiles only apply to CPU tests".to_string()),
+            (Some(profile), Component::Cpu) => profile.validate()?,
+            _ => {}
+        }
+
+        let core_mapping = match (&self.pinning, &self.component) {
+            (None, _) => None,
+            (Some(_), Component::Gpu(_)) => return Err("Thread pinning only applies to CPU tests".to_string()),
+            (Some(strategy), Component::Cpu) => Some(strategy.assign(&CpuTopology::detect()?, threads)?),
+        };
+
+        Ok(StressSession {
+            component: self.component,
+            stressor,
+            threads,
+            conditions,
+            baseline: self.baseline,
+            thermal_ceiling: self.thermal_ceiling,
+            verify: self.verify && verifiable,
+            pinning: self.pinning,
+            // constant full load is what a run without a profile does anyway
+            load_profile: self.load_profile.filter(|profile| profile.constant() != Some(100)),
+            core_mapping,
+            events: EventBus::default(),
+            token: CancellationToken::new(),
+        })
+    }
+}
+
+fn do_gpu_work(
+    session: &StressSession,
+    program: OpenCLProgram,
+    system: &mut System,
+) -> Result<Job, String> {
+    let method = session.stressor.as_ref();
+    let duration = session.conditions.duration;
+    let thermal_ceiling = session.thermal_ceiling;
+    let events = &session.events;
+    let token = &session.token;
+
+    events.emit(Event::StageChanged(Stage::Load));
+    events.emit(Event::Started { stressor: method.to_string(), unit: method.unit().to_string(), threads: None });
+    let start_time = Instant::now();
+    let energy = EnergyMeter::start();
+    let mut iterations = 0;
+    let mut sampler = Sampler::new(duration);
+
+    program.run()
+        .map_err(|error| format!("Some error has occurred while trying to do a test run to see if {method} runs on your computer. Error: {error}"))?;
+
+    let thermal_trip = thread::scope(|scope| {
+        let watchdog = scope.spawn(move || watch_thermals(thermal_ceiling, token));
+
+        while !token.is_stopped() {
+            token.wait_while_paused();
+            if let Some(duration) = duration {
+                if token.active_time(start_time) > duration {
+                    token.stop(StopReason::TimeLimit);
+                    break;
+                }
+            }
+            let mut iter_failed = false;
+
+
+            program.run().unwrap_or_else(|_| {
+                events.emit(Event::Error("Error occurred while attempting to enqueue the kernel. If this continues to happen just Control+C".to_string()));
+                iter_failed = true;
+            });
+
+            if iter_failed { continue; }
+
+            iterations += 1;
+
+            if sampler.due() {
+                let cpu_temp = sensors::cpu_temp(system, true);
+                let progress = WorkerProgress { iterations, work_done: iterations, errors: 0 };
+                sampler.sample(events, token.active_time(start_time), system, cpu_temp, vec![progress]);
+            }
+        }
+
+        watchdog.join().ok().flatten()
+    });
+
+    if let Some(trip) = &thermal_trip {
+        events.emit(Event::Error(format!("Thermal safety cutoff: {trip}")));
+    }
+
+    Ok(
+        Job {
+            name: method.to_string(),
+            total_iterations: iterations,
+            work_done: iterations,
+            unit: method.unit().to_string(),
+            errors_detected: None,
+            cpu_count: None,
+            core_mapping: vec![],
+            unpinned_cpus: vec![],
+            elapsed: token.active_time(start_time),
+            average_cpu_temp: None,
+            min_cpu_temp: None,
+            max_cpu_temp: None,
+            average_power: energy.as_ref().and_then(EnergyMeter::average_power),
+            average_cpu_usage: sampler.average_usage(),
+            fan_stats: vec![],
+            warnings: vec![],
+            steady_state: None,
+            memory_stats: None,
+            details: method.report(),
+            thermal_profile: None,
+
+            stop_reason: token.stop_reason().unwrap_or(StopReason::TimeLimit),
+            thermal_trip,
+        }
+    )
+}
+
+fn do_cpu_work(
+    session: &StressSession,
+    system: &mut System,
+) -> Result<Job, String> {
+    let method = session.stressor.as_ref();
+    let cpu_count = session.threads;
+    let conditions = &session.conditions;
+    let baseline = session.baseline;
+    let thermal_ceiling = session.thermal_ceiling;
+    let verify = session.verify;
+    let load_profile = session.load_profile.as_ref();
+    let events = &session.events;
+    let token = &session.token;
+
+    let idle_temp = baseline.and_then(|settings| {
+        events.emit(Event::StageChanged(Stage::IdleBaseline));
+        measure_idle_baseline(system, settings.idle_duration, token, events)
+    });
+
+    events.emit(Event::Started { stressor: method.to_string(), unit: method.unit().to_string(), threads: Some(cpu_count) });
+    // the first worker that could not be set up or prepared, it ends the whole run
+    let setup_error: Mutex<Option<String>> = Mutex::new(None);
+    // the first verification error of any worker
+    let worker_error: Mutex<Option<String>> = Mutex::new(None);
+    let workers: Vec<WorkerCounters> = (0..cpu_count).map(|_| WorkerCounters::default()).collect();
+    // cpus the OS did not let a worker pin itself to, the worker keeps running unpinned
+    let unpinned: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+    let target = load_profile.map(|profile| TargetLevel::new(profile.level_at(Duration::ZERO)));
+
+    // held until every worker is prepared, so the load and the clock start together
+    let start_gate = RwLock::new(());
+    let gate = start_gate.write().unwrap();
+    // every worker hangs up once it is prepared, failed or panicked
+    let (ready, prepared) = channel::<()>();
+
+    let mut job = thread::scope(|scope| {
+        let mut handles = Vec::with_capacity(cpu_count);
+        for (index, counters) in workers.iter().enumerate()
+        {
+            let setup_error = &setup_error;
+            let worker_error = &worker_error;
+            let unpinned = &unpinned;
+            let start_gate = &start_gate;
+            let ready = ready.clone();
+            let target = target.as_ref();
+            let cpu = session.core_mapping.as_ref().map(|mapping| mapping[index]);
+            let handle = scope.spawn(move ||
+                {
+                    if let Some(cpu) = cpu {
+                        if !affinity::pin_current_thread(cpu) {
+                            unpinned.lock().unwrap().push(cpu);
+                        }
+                    }
+                    let worker = method.setup()
+                        .and_then(|mut worker| worker.prepare(token).map(|_| worker));
+                    drop(ready);
+                    let mut worker = match worker {
+                        Ok(worker) => worker,
+                        Err(error) => {
+                            token.stop(StopReason::StressorFailed);
+                            setup_error.lock().unwrap().get_or_insert(error);
+                            return
+                        }
+                    };
+                    drop(start_gate.read());
+                    run_worker(worker.as_mut(), token, verify, target, counters, worker_error, events)
+                });
+            handles.push(handle);
+        }
+
+        // the thermal cutoff also covers the preparation
+        let watchdog = scope.spawn(move || watch_thermals(thermal_ceiling, token));
+        drop(ready);
+        if let Err(RecvTimeoutError::Timeout) = prepared.recv_timeout(PREPARE_NOTICE_DELAY) {
+            events.emit(Event::StageChanged(Stage::Prepare));
+            let _ = prepared.recv();
+        }
+        events.emit(Event::StageChanged(Stage::Load));
+        let start_time = Instant::now();
+        let energy = EnergyMeter::start();
+        drop(gate);
+
+        if let (Some(profile), Some(target)) = (load_profile, target.as_ref()) {
+            scope.spawn(move || drive_load(profile, start_time, token, target));
+        }
+
+        let background_report = watch_in_background(
+            conditions,
+            system,
+            start_time,
+            token,
+            &workers,
+            events,
+        );
+        let thermal_trip = watchdog.join().ok().flatten();
+
+        let stop_reason = token.stop_reason()
+            .expect("The monitor only returns once the run was stopped");
+
+        for handle in handles {
+            if handle.join().is_err() {
+                return Err("Failed to join thread".to_string());
+            }
+        }
+        // the workers are dropped by now, so the stressor has everything they collected
+        let details = method.report();
+
+        let mut total_iterations = 0;
+        let mut total_work_done = 0;
+        let mut errors = 0;
+        for progress in workers.iter().map(WorkerCounters::progress) {
+            total_iterations += progress.iterations;
+            total_work_done += progress.work_done;
+            errors += progress.errors;
+        }
+
+        // a setup failure wins even when the run was already stopped for another reason
+        if let Some(error) = setup_error.lock().unwrap().take() {
+            return Err(format!("{method} failed to start: {error}"));
+        }
+        let first_error = worker_error.lock().unwrap().take();
+
+        if let Some(trip) = &thermal_trip {
+            events.emit(Event::Error(format!("Thermal safety cutoff: {trip}")));
+        }
+
+        let mut warnings = background_report.warnings;
+        if let Some(error) = first_error {
+            warnings.push(format!("First verification error: {error}"));
+        }
+        let target_load = load_profile.and_then(LoadProfile::constant);
+        if let (Some(target), Some(measured)) = (target_load, background_report.average_cpu_usage) {
+            // the usage is averaged over every logical cpu, idle ones included
+            let logical_cpus = system.cpus().len().max(1);
+            let expected = (target as f32 * cpu_count as f32 / logical_cpus as f32).min(100.0);
+            if (measured - expected).abs() > LOAD_TOLERANCE {
+                warnings.push(format!("Measured CPU usage was {measured:.1}%, expected about {expected:.1}% for a {target}% target load"));
+            }
+        }
+        let mut unpinned = unpinned.lock().unwrap().clone();
+        if !unpinned.is_empty() {
+            unpinned.sort_unstable();
+            unpinned.dedup();
+            warnings.push(format!("Could not pin threads to CPU {}, they ran wherever the OS put them", affinity::format_cpu_list(&unpinned)));
+        }
+        for warning in &warnings {
+            events.emit(Event::Warning(warning.clone()));
+        }
+
+
+        Ok(
+            Job {
+                name: method.to_string(),
+                total_iterations,
+                work_done: total_work_done,
+                unit: method.unit().to_string(),
+                errors_detected: verify.then_some(errors),
+                cpu_count: Some(cpu_count),
+                core_mapping: session.core_mapping.clone().unwrap_or_default(),
+                unpinned_cpus: unpinned,
+                elapsed: token.active_time(start_time),
+                stop_reason,
+                average_cpu_temp: background_report.average_cpu_temp,
+                min_cpu_temp: background_report.min_cpu_temp,
+                max_cpu_temp: background_report.max_cpu_temp,
+                average_power: energy.as_ref().and_then(EnergyMeter::average_power),
+                average_cpu_usage: background_report.average_cpu_usage,
+                fan_stats: background_report.fan_stats,
+                warnings,
+                steady_state: background_report.steady_state,
+                memory_stats: Some(background_report.memory_stats),
+                details,
+                thermal_profile: None,
+                thermal_trip,
+            }
+        )
+    })?;
+
+    if let (Some(settings), Some(idle_temp)) = (baseline, idle_temp) {
+        // no point waiting for a cooldown when the thermal cutoff fired, we are about to exit, or
+        // when the user asked to stop
+        let time_to_idle = match (&job.thermal_trip, job.stop_reason) {
+            (Some(_), _) | (_, StopReason::Cancelled) => None,
+            _ => {
+                events.emit(Event::StageChanged(Stage::Cooldown));
+                watch_cooldown(system, idle_temp, settings.cooldown_tolerance, settings.cooldown_timeout, token, events)
+            }
+        };
+
+        job.thermal_profile = Some(ThermalProfile {
+            idle_temp,
+            peak_delta_over_idle: job.max_cpu_temp.map(|max| max - idle_temp),
+            average_delta_over_idle: job.average_cpu_temp.map(|average| average - idle_temp),
+            cooldown_tolerance: settings.cooldown_tolerance,
+            time_to_idle,
+        });
+    }
+
+    Ok(job)
+}
+
+fn run_worker(
+    worker: &mut dyn StressWorker,
+    token: &CancellationToken,
+    verify: bool,
+    target: Option<&TargetLevel>,
+    counters: &WorkerCounters,
+    worker_error: &Mutex<Option<String>>,
+    events: &EventBus,
+) {
+    let mut duty_cycle = DutyCycle::default();
+    while !token.is_stopped()
+    {
+        token.wait_while_paused();
+        // a level of 0 means no work at all, not one iteration per duty cycle
+        if target.is_some_and(|target| target.get() <= 0.0) {
+            duty_cycle.rest();
+            continue
+        }
+        let step_start = Instant::now();
+        let work = worker.step();
+        counters.work_done.fetch_add(work, Ordering::Relaxed);
+        counters.iterations.fetch_add(1, Ordering::Relaxed);
+
+        if verify {
+            if let Err(error) = worker.verify() {
+                counters.errors.fetch_add(1, Ordering::Relaxed);
+                let mut first_error = worker_error.lock().unwrap();
+                // only the first mismatch is reported live, the rest end up in the error count
+                if first_error.is_none() {
+                    events.emit(Event::Error(format!("Verification failed: {error}")));
+                    *first_error = Some(error);
+                }
+            }
+        }
+
+        if let Some(target) = target {
+            duty_cycle.throttle(step_start.elapsed(), target.get(), token);
+        }
+    }
+}
@@ -1,30 +1,297 @@
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 use ocl::{Buffer, Context, Device, Kernel, Platform, Program, Queue};
 use ocl::core::{DeviceInfo, DeviceInfoResult};
+use crate::control::CancellationToken;
 
-#[derive(Clone, Eq, PartialEq)]
-pub enum Stressor {
-    Fibonacci,
-    Primes,
-    MatrixMultiplication,
-    FloatAddition,
-    FloatMultiplication,
-    FloatDivision,
-    SquareRoot,
-    QuakeInverseSquareRoot,
+pub mod cache;
+mod compression;
+mod crypto;
+pub mod disk;
+mod integer;
+
+/// A workload that can be run by a [`crate::StressSession`]. Built-in stressors and your own
+/// are treated the same way, register them in a [`StressorRegistry`] to make them selectable.
+pub trait Stressor: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// What [`StressWorker::step`] reports its work in, e.g. "iterations" or "bytes"
+    fn unit(&self) -> &str {
+        "iterations"
+    }
+
+    fn runs_on_cpu(&self) -> bool {
+        true
+    }
+
+    fn runs_on_gpu(&self) -> bool {
+        false
+    }
+
+    /// Whether [`StressWorker::verify`] actually checks anything
+    fn verifiable(&self) -> bool {
+        false
+    }
+
+    /// Called once on every worker thread before the load starts, so any buffers the
+    /// workload needs are allocated by the thread that uses them.
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String>;
+
+    fn opencl_program(&self, _ctx: &OpenCLContext) -> Result<OpenCLProgram, String> {
+        Err(format!("{} does not run on the GPU", self.name()))
+    }
+
+    /// Extra results as label and value pairs, e.g. latency percentiles, shown with the job.
+    /// Called once after every run, so whatever was collected should be reset here.
+    fn report(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+}
+
+/// Per thread state of a [`Stressor`]
+pub trait StressWorker: Send {
+    /// Slow one-time work such as filling a test file, it runs before the clock starts. Should
+    /// return early once `token` is stopped.
+    fn prepare(&mut self, _token: &CancellationToken) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Runs one iteration of the workload and returns how much work was done in [`Stressor::unit`]
+    fn step(&mut self) -> u64;
+
+    /// Checks the result of the last [`StressWorker::step`]. A mismatch usually means the
+    /// hardware is unstable.
+    fn verify(&mut self) -> Result<(), String> {
+        Ok(())
+    }
 }
 
-impl Display for Stressor {
+impl Display for dyn Stressor + '_ {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Stressor::Fibonacci => f.write_str("Fibonacci"),
-            Stressor::Primes => f.write_str("Primes"),
-            Stressor::MatrixMultiplication => f.write_str("Matrix Multiplication"),
-            Stressor::FloatAddition => f.write_str("Float Addition"),
-            Stressor::FloatMultiplication => f.write_str("Float Multiplication"),
-            Stressor::FloatDivision => f.write_str("Float Division"),
-            Stressor::SquareRoot => f.write_str("Square Root"),
-            Stressor::QuakeInverseSquareRoot => f.write_str("Quake Inverse Square Root")
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct StressorRegistry {
+    stressors: Vec<Arc<dyn Stressor>>,
+}
+
+impl StressorRegistry {
+    pub fn new() -> Self {
+        StressorRegistry::default()
+    }
+
+    /// A registry with every stressor that ships with ezstress
+    pub fn with_builtins() -> Self {
+        let mut registry = StressorRegistry::new();
+        for stressor in builtin_stressors() {
+            registry.register(stressor);
+        }
+        registry.register(PrimesStressor);
+        for stressor in integer::integer_stressors() {
+            registry.register(stressor);
+        }
+        registry.register(crypto::Sha256Stressor);
+        registry.register(crypto::AesGcmStressor);
+        registry.register(compression::CompressionStressor);
+        for stressor in cache::cache_stressors(&cache::detect_caches()) {
+            registry.register(stressor);
+        }
+        registry
+    }
+
+    /// Adds a stressor, replacing any previously registered stressor with the same name
+    pub fn register(&mut self, stressor: impl Stressor + 'static) {
+        self.stressors.retain(|existing| existing.name() != stressor.name());
+        self.stressors.push(Arc::new(stressor));
+    }
+
+    pub fn find(&self, name: &str) -> Option<Arc<dyn Stressor>> {
+        self.stressors
+            .iter()
+            .find(|stressor| stressor.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    pub fn all(&self) -> &[Arc<dyn Stressor>] {
+        &self.stressors
+    }
+
+    pub fn cpu_stressors(&self) -> Vec<Arc<dyn Stressor>> {
+        self.stressors.iter().filter(|stressor| stressor.runs_on_cpu()).cloned().collect()
+    }
+
+    pub fn gpu_stressors(&self) -> Vec<Arc<dyn Stressor>> {
+        self.stressors.iter().filter(|stressor| stressor.runs_on_gpu()).cloned().collect()
+    }
+}
+
+/// SplitMix64, cheap enough that the generator does not dominate the workload
+#[derive(Clone, Copy)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+struct OpenCLKernel {
+    source: &'static str,
+    name: &'static str,
+    args: fn() -> Vec<Vec<f32>>,
+}
+
+/// The original stressors, each is a plain function that is called in a loop
+struct BuiltinStressor {
+    name: &'static str,
+    description: &'static str,
+    cpu: fn(),
+    gpu: Option<OpenCLKernel>,
+}
+
+struct BuiltinWorker(fn());
+
+impl StressWorker for BuiltinWorker {
+    fn step(&mut self) -> u64 {
+        (self.0)();
+        1
+    }
+}
+
+impl Stressor for BuiltinStressor {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn runs_on_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        Ok(Box::new(BuiltinWorker(self.cpu)))
+    }
+
+    fn opencl_program(&self, ctx: &OpenCLContext) -> Result<OpenCLProgram, String> {
+        match &self.gpu {
+            Some(kernel) => OpenCLProgram::new(ctx, kernel.source, kernel.name, (kernel.args)()),
+            None => Err(format!("{} does not run on the GPU", self.name)),
+        }
+    }
+}
+
+fn builtin_stressors() -> Vec<BuiltinStressor> {
+    vec![
+        BuiltinStressor {
+            name: "Fibonacci",
+            description: "Integer additions in a tight dependency chain",
+            cpu: fibonacci_cpu,
+            gpu: None,
+        },
+        BuiltinStressor {
+            name: "Float Addition",
+            description: "Repeated floating point additions",
+            cpu: float_add,
+            gpu: Some(OpenCLKernel {
+                source: OPENCL_FLOAT_ADD,
+                name: "float_add",
+                args: || vec![vec![952.139_1_f32; OPENCL_VECTOR_SIZE], vec![0_f32; OPENCL_VECTOR_SIZE]],
+            }),
+        },
+        BuiltinStressor {
+            name: "Float Multiplication",
+            description: "Repeated floating point multiplications",
+            cpu: float_mul,
+            gpu: None,
+        },
+        BuiltinStressor {
+            name: "Matrix Multiplication",
+            description: "Naive 100x100 matrix multiplication",
+            cpu: matrix_multiplication,
+            gpu: Some(OpenCLKernel {
+                source: OPENCL_MATRIX_MULTIPLICATION,
+                name: "matrix_mult",
+                args: || vec![
+                    vec![201_231_231.231139_13_f32; OPENCL_VECTOR_SIZE],
+                    vec![231_231.23_f32; OPENCL_VECTOR_SIZE],
+                    vec![0_f32; OPENCL_VECTOR_SIZE],
+                ],
+            }),
+        },
+        BuiltinStressor {
+            name: "Square Root",
+            description: "Repeated square roots",
+            cpu: || sqrt_cpu(std::hint::black_box(1_143_243_423.112_354_3)),
+            // yeah, lets spam sqrt 952 on gpu
+            gpu: Some(OpenCLKernel {
+                source: OPENCL_SQUARE_ROOT,
+                name: "sqrt",
+                args: || vec![vec![1_000_000.94_f32; OPENCL_VECTOR_SIZE], vec![0_f32; OPENCL_VECTOR_SIZE]],
+            }),
+        },
+        BuiltinStressor {
+            name: "Quake Inverse Square Root",
+            description: "The Quake III fast inverse square root bit hack",
+            cpu: || quake_rsqrt(std::hint::black_box(1_143_243_423.112_354_3)),
+            gpu: None,
+        },
+        BuiltinStressor {
+            name: "Float Division",
+            description: "Repeated floating point divisions",
+            cpu: float_division,
+            gpu: None,
+        },
+    ]
+}
+
+// there are exactly 9592 primes below 100,000
+const PRIMES_BELOW_100_000: u32 = 9592;
+
+struct PrimesStressor;
+
+struct PrimesWorker {
+    last_count: u32,
+}
+
+impl Stressor for PrimesStressor {
+    fn name(&self) -> &str {
+        "Primes"
+    }
+
+    fn description(&self) -> &str {
+        "Trial division of every number below 100,000"
+    }
+
+    fn verifiable(&self) -> bool {
+        true
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        Ok(Box::new(PrimesWorker { last_count: 0 }))
+    }
+}
+
+impl StressWorker for PrimesWorker {
+    fn step(&mut self) -> u64 {
+        self.last_count = primes();
+        1
+    }
+
+    fn verify(&mut self) -> Result<(), String> {
+        match self.last_count {
+            PRIMES_BELOW_100_000 => Ok(()),
+            count => Err(format!("Found {count} primes below 100,000, expected {PRIMES_BELOW_100_000}")),
         }
     }
 }
@@ -171,13 +438,14 @@ pub fn float_add() {
     }
 }
 
-pub fn primes() {
-    let mut _primes = 0;
+pub fn primes() -> u32 {
+    let mut primes = 0;
     for i in 2..100000 {
         if is_prime(i) {
-            _primes += 1;
+            primes += 1;
         }
     }
+    primes
 }
 
 fn is_prime(n: i32) -> bool {
@@ -208,8 +476,8 @@ pub fn matrix_multiplication() {
         let mut result = [[0.0; 100]; 100];
         for i in 0..100 {
             for j in 0..100 {
-                for k in 0..100 {
-                    result[i][j] += matrix[i][k] * matrix[k][j];
+                for (k, row) in matrix.iter().enumerate() {
+                    result[i][j] += matrix[i][k] * row[j];
                 }
             }
         }
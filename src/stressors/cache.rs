@@ -0,0 +1,307 @@
+use std::hint::black_box;
+use std::time::Duration;
+use super::{SplitMix64, Stressor, StressWorker};
+
+const CACHE_LINE: usize = 64;
+// every iteration does at least this much, so tiny L1 working sets are not dominated by the
+// per iteration bookkeeping
+const MIN_LOADS_PER_ITERATION: u64 = 1 << 20;
+const MIN_BYTES_PER_ITERATION: u64 = 64 << 20;
+/// How long `ezstress cache` runs every stressor
+pub const DEFAULT_CACHE_TEST_DURATION: Duration = Duration::from_secs(5);
+const SEED: u64 = 0x9e6c_63d0_676a_9a99;
+
+/// A data or unified cache of the first cpu
+#[derive(Clone, Copy, Debug)]
+pub struct CacheLevel {
+    pub level: u8,
+    /// In bytes
+    pub size: usize,
+}
+
+impl CacheLevel {
+    /// Half the cache, enough to miss the level below while leaving room for everything else
+    pub fn working_set(&self) -> usize {
+        (self.size / 2).max(CACHE_LINE)
+    }
+}
+
+/// Reads the caches of cpu0 from `/sys/devices/system/cpu/cpu0/cache`, instruction caches are
+/// skipped. Empty when the caches can not be read.
+#[cfg(target_os = "linux")]
+pub fn detect_caches() -> Vec<CacheLevel> {
+    use std::fs;
+
+    let Ok(entries) = fs::read_dir("/sys/devices/system/cpu/cpu0/cache") else {
+        return vec![]
+    };
+
+    let mut caches: Vec<CacheLevel> = entries.flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("index"))
+        .filter_map(|entry| {
+            let read = |file: &str| fs::read_to_string(entry.path().join(file)).ok().map(|value| value.trim().to_string());
+            if read("type")? == "Instruction" {
+                return None
+            }
+            Some(CacheLevel {
+                level: read("level")?.parse().ok()?,
+                size: parse_cache_size(&read("size")?)?,
+            })
+        })
+        .collect();
+    caches.sort_by_key(|cache| cache.level);
+    caches.dedup_by_key(|cache| cache.level);
+    caches
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn detect_caches() -> Vec<CacheLevel> {
+    vec![]
+}
+
+/// Parses sizes like `48K` or `32M`, None for empty caches and sizes that do not fit a usize
+fn parse_cache_size(size: &str) -> Option<usize> {
+    let (number, multiplier) = match size.chars().last()? {
+        'K' => (&size[..size.len() - 1], 1024),
+        'M' => (&size[..size.len() - 1], 1024 * 1024),
+        'G' => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    number.parse::<usize>().ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .filter(|size| *size > 0)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CacheAccess {
+    /// Dependent loads in random order, measures latency as every load waits for the one before
+    PointerChase,
+    /// Sequential reads the prefetchers can keep ahead of, measures bandwidth
+    Streaming,
+}
+
+/// Keeps its working set inside one cache level, one stressor per level and access pattern
+pub struct CacheStressor {
+    name: String,
+    description: String,
+    pub cache: CacheLevel,
+    pub access: CacheAccess,
+}
+
+impl CacheStressor {
+    pub fn new(cache: CacheLevel, access: CacheAccess) -> Self {
+        let working_set = format_size(cache.working_set());
+        let (name, description) = match access {
+            CacheAccess::PointerChase => (
+                format!("L{} Cache Latency", cache.level),
+                format!("Random pointer chasing through {working_set}, one dependent load per cache line"),
+            ),
+            CacheAccess::Streaming => (
+                format!("L{} Cache Bandwidth", cache.level),
+                format!("Sequential reads of {working_set}"),
+            ),
+        };
+        CacheStressor { name, description, cache, access }
+    }
+}
+
+impl Stressor for CacheStressor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn unit(&self) -> &str {
+        match self.access {
+            CacheAccess::PointerChase => "loads",
+            CacheAccess::Streaming => "bytes",
+        }
+    }
+
+    fn verifiable(&self) -> bool {
+        true
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        let lines = self.cache.working_set() / CACHE_LINE;
+        match self.access {
+            CacheAccess::PointerChase => Ok(Box::new(PointerChaseWorker::new(lines))),
+            CacheAccess::Streaming => Ok(Box::new(StreamingWorker::new(lines * CACHE_LINE / size_of::<u64>()))),
+        }
+    }
+}
+
+/// A latency and a bandwidth stressor for every level
+pub fn cache_stressors(caches: &[CacheLevel]) -> Vec<CacheStressor> {
+    caches.iter()
+        .flat_map(|cache| [CacheAccess::PointerChase, CacheAccess::Streaming].map(|access| CacheStressor::new(*cache, access)))
+        .collect()
+}
+
+pub fn format_size(bytes: usize) -> String {
+    match bytes {
+        bytes if bytes >= 1024 * 1024 => format!("{:.1} MiB", bytes as f64 / 1024.0 / 1024.0),
+        bytes => format!("{} KiB", bytes / 1024),
+    }
+}
+
+/// Only `next` is used, the alignment spreads the nodes over separate cache lines
+#[repr(align(64))]
+#[derive(Clone, Copy)]
+struct CacheLine {
+    next: usize,
+}
+
+struct PointerChaseWorker {
+    lines: Vec<CacheLine>,
+    loads_per_step: u64,
+    position: usize,
+}
+
+impl PointerChaseWorker {
+    fn new(count: usize) -> Self {
+        // Sattolo's algorithm gives a single cycle through every line, in an order the
+        // prefetchers can not guess
+        let mut order: Vec<usize> = (0..count).collect();
+        let mut rng = SplitMix64(SEED);
+        for i in (1..count).rev() {
+            let j = (rng.next() % i as u64) as usize;
+            order.swap(i, j);
+        }
+
+        let mut lines = vec![CacheLine { next: 0 }; count];
+        for (line, next) in lines.iter_mut().zip(order) {
+            line.next = next;
+        }
+
+        // whole laps only, so every step ends where it started
+        let laps = MIN_LOADS_PER_ITERATION.div_ceil(count as u64);
+        PointerChaseWorker { lines, loads_per_step: laps * count as u64, position: 0 }
+    }
+}
+
+impl StressWorker for PointerChaseWorker {
+    fn step(&mut self) -> u64 {
+        let mut position = self.position;
+        for _ in 0..self.loads_per_step {
+            position = self.lines[position].next;
+        }
+        self.position = black_box(position);
+        self.loads_per_step
+    }
+
+    fn verify(&mut self) -> Result<(), String> {
+        match self.position {
+            0 => Ok(()),
+            position => Err(format!("The pointer chase ended on line {position} instead of where it started")),
+        }
+    }
+}
+
+struct StreamingWorker {
+    data: Vec<u64>,
+    passes: u64,
+    expected: u64,
+    last: u64,
+}
+
+impl StreamingWorker {
+    fn new(count: usize) -> Self {
+        let mut rng = SplitMix64(SEED);
+        let data: Vec<u64> = (0..count).map(|_| rng.next()).collect();
+        let expected = stream_sum(&data);
+        let passes = MIN_BYTES_PER_ITERATION.div_ceil((count * size_of::<u64>()) as u64);
+        StreamingWorker { data, passes, expected, last: expected }
+    }
+}
+
+/// Four accumulators so the adds keep up with the loads
+fn stream_sum(data: &[u64]) -> u64 {
+    let mut sums = [0u64; 4];
+    for chunk in data.chunks_exact(4) {
+        for (sum, value) in sums.iter_mut().zip(chunk) {
+            *sum = sum.wrapping_add(*value);
+        }
+    }
+    let remainder = data.chunks_exact(4).remainder().iter().fold(0u64, |sum, value| sum.wrapping_add(*value));
+    sums.iter().fold(remainder, |total, sum| total.wrapping_add(*sum))
+}
+
+impl StressWorker for StreamingWorker {
+    fn step(&mut self) -> u64 {
+        let mut passes = 0;
+        while passes < self.passes {
+            self.last = stream_sum(black_box(&self.data));
+            passes += 1;
+            if self.last != self.expected {
+                break
+            }
+        }
+        // a mismatch ends the step early, only the passes that ran count
+        passes * (self.data.len() * size_of::<u64>()) as u64
+    }
+
+    fn verify(&mut self) -> Result<(), String> {
+        match self.last == self.expected {
+            true => Ok(()),
+            false => Err(format!("Read a sum of {:#018x} instead of {:#018x}", self.last, self.expected)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_sizes_parse_with_and_without_suffix() {
+        assert_eq!(parse_cache_size("48K"), Some(48 * 1024));
+        assert_eq!(parse_cache_size("2M"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_cache_size("1G"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_cache_size("32768"), Some(32768));
+    }
+
+    #[test]
+    fn unusable_cache_sizes_are_rejected() {
+        assert_eq!(parse_cache_size(""), None);
+        assert_eq!(parse_cache_size("K"), None);
+        assert_eq!(parse_cache_size("0K"), None);
+        assert_eq!(parse_cache_size("0"), None);
+        assert_eq!(parse_cache_size("12T"), None);
+        assert_eq!(parse_cache_size("-4K"), None);
+        assert_eq!(parse_cache_size(&format!("{}G", usize::MAX / 1024)), None);
+        assert_eq!(parse_cache_size(&format!("{}0", usize::MAX)), None);
+    }
+
+    #[test]
+    fn pointer_chase_is_a_single_cycle_through_every_line() {
+        for count in [1, 2, 3, 64, 1000] {
+            let worker = PointerChaseWorker::new(count);
+            let mut visited = vec![false; count];
+            let mut position = 0;
+            for _ in 0..count {
+                assert!(!visited[position], "line {position} visited twice with {count} lines");
+                visited[position] = true;
+                position = worker.lines[position].next;
+            }
+            assert_eq!(position, 0, "the cycle of {count} lines does not close");
+            assert!(visited.iter().all(|visited| *visited));
+            assert_eq!(worker.loads_per_step % count as u64, 0);
+        }
+    }
+
+    #[test]
+    fn streaming_counts_only_the_passes_that_ran() {
+        let mut worker = StreamingWorker::new(1024);
+        let pass = (1024 * size_of::<u64>()) as u64;
+        assert_eq!(worker.step(), worker.passes * pass);
+        assert!(worker.verify().is_ok());
+
+        worker.data[100] ^= 1;
+        assert_eq!(worker.step(), pass);
+        assert!(worker.verify().is_err());
+    }
+}
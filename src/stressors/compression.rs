@@ -0,0 +1,96 @@
+use std::hint::black_box;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec;
+use super::{SplitMix64, Stressor, StressWorker};
+
+const CORPUS_MB: u64 = 2;
+const CORPUS_SIZE: usize = CORPUS_MB as usize * 1_000_000;
+// zlib's default, a realistic mix of match searching and literal coding
+const COMPRESSION_LEVEL: u8 = 6;
+const SEED: u64 = 0xd1b5_4a32_d192_ed03;
+const WORDS: [&str; 32] = [
+    "the", "of", "and", "to", "in", "is", "that", "for", "it", "as", "with", "was", "on", "be",
+    "by", "this", "stress", "thread", "core", "cache", "memory", "voltage", "clock", "thermal",
+    "power", "error", "stable", "load", "test", "result", "throughput", "frequency",
+];
+
+/// Mostly text with a skewed word distribution, with some random bytes mixed in so the
+/// compressor also has to deal with data it can not shrink
+fn generate_corpus() -> Vec<u8> {
+    let mut rng = SplitMix64(SEED);
+    let mut corpus = Vec::with_capacity(CORPUS_SIZE);
+    while corpus.len() < CORPUS_SIZE {
+        let value = rng.next();
+        match value % 16 {
+            0 => corpus.extend_from_slice(&rng.next().to_le_bytes()),
+            1 => corpus.extend_from_slice(format!("{} ", value >> 40).as_bytes()),
+            _ => {
+                // the product of two uniform picks favours the first, frequent words
+                let word = ((value >> 8) % 32) * ((value >> 16) % 32) / 32;
+                corpus.extend_from_slice(WORDS[word as usize].as_bytes());
+                corpus.push(if value >> 32 & 15 == 0 { b'\n' } else { b' ' });
+            }
+        }
+    }
+    corpus.truncate(CORPUS_SIZE);
+    corpus
+}
+
+pub(super) struct CompressionStressor;
+
+struct CompressionWorker {
+    corpus: Vec<u8>,
+    compressed_size: usize,
+    last_error: Option<String>,
+}
+
+impl Stressor for CompressionStressor {
+    fn name(&self) -> &str {
+        "Compression Round Trip"
+    }
+
+    fn description(&self) -> &str {
+        "DEFLATE compression and decompression of a 2 MB generated corpus, checked byte for byte"
+    }
+
+    fn unit(&self) -> &str {
+        "MB"
+    }
+
+    fn verifiable(&self) -> bool {
+        true
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        let corpus = generate_corpus();
+        let compressed_size = compress_to_vec(&corpus, COMPRESSION_LEVEL).len();
+        Ok(Box::new(CompressionWorker { corpus, compressed_size, last_error: None }))
+    }
+}
+
+impl StressWorker for CompressionWorker {
+    fn step(&mut self) -> u64 {
+        let compressed = compress_to_vec(black_box(&self.corpus), COMPRESSION_LEVEL);
+        let decompressed = decompress_to_vec(&compressed);
+        self.last_error = match decompressed {
+            // the compressor is deterministic, a different size already means a corrupted run
+            _ if compressed.len() != self.compressed_size => {
+                Some(format!("Compressed to {} bytes instead of {}", compressed.len(), self.compressed_size))
+            }
+            Ok(decompressed) if decompressed == self.corpus => None,
+            Ok(decompressed) => match decompressed.iter().zip(&self.corpus).position(|(actual, expected)| actual != expected) {
+                Some(offset) => Some(format!("Round trip differs at byte {offset}")),
+                None => Some(format!("Round trip returned {} bytes instead of {}", decompressed.len(), self.corpus.len())),
+            },
+            Err(e) => Some(format!("Decompression failed: {e:?}")),
+        };
+        CORPUS_MB
+    }
+
+    fn verify(&mut self) -> Result<(), String> {
+        match self.last_error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
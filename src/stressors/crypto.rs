@@ -0,0 +1,161 @@
+use std::hint::black_box;
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use aes_gcm::aead::AeadInPlace;
+use sha2::{Digest, Sha256};
+use super::{SplitMix64, Stressor, StressWorker};
+
+// a whole number of megabytes, so throughput can be counted in MB
+const BUFFER_MB: u64 = 4;
+const BUFFER_SIZE: usize = BUFFER_MB as usize * 1_000_000;
+const SEED: u64 = 0x5851_f42d_4c95_7f2d;
+const KEY: [u8; 32] = [0x42; 32];
+const NONCE: [u8; 12] = [0x24; 12];
+
+// FIPS 180-2, appendix B.1
+const SHA256_KAT_INPUT: &[u8] = b"abc";
+const SHA256_KAT_DIGEST: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+// McGrew and Viega, "The Galois/Counter Mode of Operation", test case 14: 256-bit zero key,
+// zero IV and a single zero block
+const AES_GCM_KAT_CIPHERTEXT: &str = "cea7403d4d606b6e074ec5d3baf39d18";
+const AES_GCM_KAT_TAG: &str = "d0d1c8a799996bf0265b98b5d48ab919";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn random_buffer() -> Vec<u8> {
+    let mut rng = SplitMix64(SEED);
+    (0..BUFFER_SIZE / 8).flat_map(|_| rng.next().to_le_bytes()).collect()
+}
+
+/// Checks the implementation against published test vectors before any load runs, a wrong
+/// reference would make every iteration look fine
+fn known_answer(name: &str, result: String, expected: &str) -> Result<(), String> {
+    match result == expected {
+        true => Ok(()),
+        false => Err(format!("{name} known-answer test failed, got {result} instead of {expected}")),
+    }
+}
+
+pub(super) struct Sha256Stressor;
+
+struct Sha256Worker {
+    data: Vec<u8>,
+    expected: [u8; 32],
+    last: [u8; 32],
+}
+
+impl Stressor for Sha256Stressor {
+    fn name(&self) -> &str {
+        "SHA-256"
+    }
+
+    fn description(&self) -> &str {
+        "SHA-256 hashing of a 4 MB buffer, uses the SHA extensions when the CPU has them"
+    }
+
+    fn unit(&self) -> &str {
+        "MB"
+    }
+
+    fn verifiable(&self) -> bool {
+        true
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        known_answer("SHA-256", hex(&Sha256::digest(SHA256_KAT_INPUT)), SHA256_KAT_DIGEST)?;
+
+        let data = random_buffer();
+        let expected: [u8; 32] = Sha256::digest(&data).into();
+        Ok(Box::new(Sha256Worker { data, expected, last: expected }))
+    }
+}
+
+impl StressWorker for Sha256Worker {
+    fn step(&mut self) -> u64 {
+        self.last = Sha256::digest(black_box(&self.data)).into();
+        BUFFER_MB
+    }
+
+    fn verify(&mut self) -> Result<(), String> {
+        match self.last == self.expected {
+            true => Ok(()),
+            false => Err(format!("Digest {} does not match {}", hex(&self.last), hex(&self.expected))),
+        }
+    }
+}
+
+pub(super) struct AesGcmStressor;
+
+struct AesGcmWorker {
+    cipher: Aes256Gcm,
+    plaintext: Vec<u8>,
+    buffer: Vec<u8>,
+    expected: [u8; 16],
+    last: [u8; 16],
+}
+
+impl AesGcmWorker {
+    /// Encrypts a fresh copy of the plaintext and returns the tag, which covers every ciphertext byte
+    fn encrypt(&mut self) -> Result<[u8; 16], String> {
+        self.buffer.copy_from_slice(&self.plaintext);
+        self.cipher.encrypt_in_place_detached(Nonce::from_slice(&NONCE), b"", &mut self.buffer)
+            .map(Into::into)
+            .map_err(|e| format!("Encryption failed: {e}"))
+    }
+}
+
+impl Stressor for AesGcmStressor {
+    fn name(&self) -> &str {
+        "AES-256-GCM"
+    }
+
+    fn description(&self) -> &str {
+        "AES-256-GCM encryption of a 4 MB buffer, uses AES-NI and carry-less multiplication when the CPU has them"
+    }
+
+    fn unit(&self) -> &str {
+        "MB"
+    }
+
+    fn verifiable(&self) -> bool {
+        true
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        let mut block = [0u8; 16];
+        let tag = Aes256Gcm::new(&Key::<Aes256Gcm>::from([0u8; 32]))
+            .encrypt_in_place_detached(Nonce::from_slice(&[0u8; 12]), b"", &mut block)
+            .map_err(|e| format!("AES-256-GCM known-answer test failed: {e}"))?;
+        known_answer("AES-256-GCM", hex(&block), AES_GCM_KAT_CIPHERTEXT)?;
+        known_answer("AES-256-GCM", hex(&tag), AES_GCM_KAT_TAG)?;
+
+        let plaintext = random_buffer();
+        let mut worker = AesGcmWorker {
+            cipher: Aes256Gcm::new(&Key::<Aes256Gcm>::from(KEY)),
+            buffer: vec![0; plaintext.len()],
+            plaintext,
+            expected: [0; 16],
+            last: [0; 16],
+        };
+        worker.expected = worker.encrypt()?;
+        worker.last = worker.expected;
+        Ok(Box::new(worker))
+    }
+}
+
+impl StressWorker for AesGcmWorker {
+    fn step(&mut self) -> u64 {
+        // encrypting a buffer of this size can not fail, a zeroed tag is caught by verify anyway
+        self.last = self.encrypt().unwrap_or_default();
+        BUFFER_MB
+    }
+
+    fn verify(&mut self) -> Result<(), String> {
+        match self.last == self.expected {
+            true => Ok(()),
+            false => Err(format!("Tag {} does not match {}", hex(&self.last), hex(&self.expected))),
+        }
+    }
+}
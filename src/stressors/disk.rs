@@ -0,0 +1,527 @@
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use super::{SplitMix64, Stressor, StressWorker};
+use crate::control::CancellationToken;
+
+/// O_DIRECT needs buffers, offsets and lengths aligned to the logical block size of the device,
+/// 4 KiB covers every common one
+pub const DIRECT_IO_ALIGNMENT: usize = 4096;
+pub const DEFAULT_FILE_SIZE: u64 = 256 * 1024 * 1024;
+pub const DEFAULT_BLOCK_SIZE: usize = 4096;
+const SEED: u64 = 0xa076_1d64_78bd_642f;
+
+// 32 sub-buckets per power of two keep every percentile within about 3% of the real latency
+const SUB_BUCKET_BITS: u32 = 5;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+// one row for the values below SUB_BUCKETS plus one per power of two above it
+const LATENCY_BUCKETS: usize = (64 - SUB_BUCKET_BITS as usize + 1) * SUB_BUCKETS;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DiskAccess {
+    Sequential,
+    Random,
+}
+
+#[derive(Clone, Debug)]
+pub struct DiskSettings {
+    /// Where the test files are created, one per worker thread
+    pub directory: PathBuf,
+    /// Size of every worker's file in bytes, it is filled once before the load starts
+    pub file_size: u64,
+    /// Bytes per read or write
+    pub block_size: usize,
+    pub access: DiskAccess,
+    /// Share of the operations that are reads, the rest are writes
+    pub read_percent: u8,
+    /// Bypass the page cache with O_DIRECT, only supported on Linux
+    pub direct: bool,
+    /// fsync after every write
+    pub fsync: bool,
+}
+
+impl Default for DiskSettings {
+    fn default() -> Self {
+        DiskSettings {
+            directory: std::env::temp_dir(),
+            file_size: DEFAULT_FILE_SIZE,
+            block_size: DEFAULT_BLOCK_SIZE,
+            access: DiskAccess::Random,
+            read_percent: 50,
+            direct: cfg!(target_os = "linux"),
+            fsync: true,
+        }
+    }
+}
+
+impl DiskSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        let alignment = match self.direct {
+            true => DIRECT_IO_ALIGNMENT,
+            false => size_of::<u64>(),
+        };
+        if self.block_size == 0 || !self.block_size.is_multiple_of(alignment) {
+            return Err(format!("The block size has to be a multiple of {alignment} bytes"))
+        }
+        if self.file_size < self.block_size as u64 {
+            return Err("The file has to hold at least one block".to_string())
+        }
+        if self.read_percent > 100 {
+            return Err("The read share has to be between 0 and 100 percent".to_string())
+        }
+        if self.direct && !cfg!(target_os = "linux") {
+            return Err("Direct I/O is only supported on Linux, turn it off to test this system".to_string())
+        }
+        if !self.directory.is_dir() {
+            return Err(format!("{} is not a directory", self.directory.display()))
+        }
+        Ok(())
+    }
+}
+
+impl Display for DiskSettings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let access = match self.access {
+            DiskAccess::Sequential => "sequential",
+            DiskAccess::Random => "random",
+        };
+        write!(f, "{access} {} KiB blocks, {}% reads, {} MiB per thread in {}",
+               self.block_size / 1024, self.read_percent, self.file_size / 1024 / 1024, self.directory.display())?;
+        if self.direct {
+            f.write_str(", O_DIRECT")?;
+        }
+        if self.fsync {
+            f.write_str(", fsync after writes")?;
+        }
+        Ok(())
+    }
+}
+
+/// Latencies in nanoseconds, log-linear buckets so millions of operations fit in a few KiB
+#[derive(Clone)]
+struct LatencyHistogram {
+    counts: Vec<u64>,
+    total: u64,
+    max: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram { counts: vec![0; LATENCY_BUCKETS], total: 0, max: 0 }
+    }
+}
+
+impl LatencyHistogram {
+    fn bucket(nanos: u64) -> usize {
+        if nanos < SUB_BUCKETS as u64 {
+            return nanos as usize
+        }
+        let shift = 63 - nanos.leading_zeros() - SUB_BUCKET_BITS;
+        let mantissa = (nanos >> shift) as usize & (SUB_BUCKETS - 1);
+        ((shift as usize + 1) << SUB_BUCKET_BITS) | mantissa
+    }
+
+    /// The lowest latency that falls into `bucket`
+    fn bucket_floor(bucket: usize) -> u64 {
+        if bucket < SUB_BUCKETS {
+            return bucket as u64
+        }
+        let shift = (bucket >> SUB_BUCKET_BITS) - 1;
+        ((SUB_BUCKETS | (bucket & (SUB_BUCKETS - 1))) as u64) << shift
+    }
+
+    fn record(&mut self, latency: Duration) {
+        let nanos = latency.as_nanos().min(u64::MAX as u128) as u64;
+        self.counts[LatencyHistogram::bucket(nanos)] += 1;
+        self.total += 1;
+        self.max = self.max.max(nanos);
+    }
+
+    fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+
+    /// `percentile` between 0 and 100
+    fn percentile(&self, percentile: f64) -> Duration {
+        let rank = ((self.total as f64 * percentile / 100.0).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_nanos(LatencyHistogram::bucket_floor(bucket).min(self.max))
+            }
+        }
+        Duration::from_nanos(self.max)
+    }
+}
+
+/// What every worker did, merged when the workers are dropped at the end of a run
+#[derive(Default)]
+struct IoStats {
+    reads: u64,
+    writes: u64,
+    bytes: u64,
+    io_errors: u64,
+    latency: LatencyHistogram,
+    /// From the first operation of any worker to the last one, the file preparation is excluded
+    window: Option<(Instant, Instant)>,
+}
+
+impl IoStats {
+    fn merge(&mut self, other: &IoStats) {
+        self.reads += other.reads;
+        self.writes += other.writes;
+        self.bytes += other.bytes;
+        self.io_errors += other.io_errors;
+        self.latency.merge(&other.latency);
+        self.window = match (self.window, other.window) {
+            (Some((start, end)), Some((other_start, other_end))) => Some((start.min(other_start), end.max(other_end))),
+            (window, None) | (None, window) => window,
+        };
+    }
+}
+
+/// Reads and writes blocks of a file per worker thread. The queue depth is the number of
+/// threads: every thread does synchronous I/O, so each keeps one operation in flight. Every
+/// block holds data derived from its index and how often it was written, so any read can be
+/// checked without keeping a copy of the file in memory.
+pub struct DiskStressor {
+    settings: DiskSettings,
+    description: String,
+    next_file: AtomicUsize,
+    stats: Arc<Mutex<IoStats>>,
+}
+
+impl DiskStressor {
+    pub fn new(settings: DiskSettings) -> Result<DiskStressor, String> {
+        settings.validate()?;
+        Ok(DiskStressor {
+            description: settings.to_string(),
+            settings,
+            next_file: AtomicUsize::new(0),
+            stats: Arc::default(),
+        })
+    }
+}
+
+impl Stressor for DiskStressor {
+    fn name(&self) -> &str {
+        "Disk I/O"
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn unit(&self) -> &str {
+        "bytes"
+    }
+
+    fn verifiable(&self) -> bool {
+        true
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        let file_number = self.next_file.fetch_add(1, Ordering::Relaxed);
+        let path = self.settings.directory.join(format!("ezstress-io-{}-{file_number}.tmp", std::process::id()));
+        DiskWorker::new(self.settings.clone(), path, self.stats.clone(), file_number as u64)
+            .map(|worker| Box::new(worker) as Box<dyn StressWorker>)
+    }
+
+    fn report(&self) -> Vec<(String, String)> {
+        let stats = std::mem::take(&mut *self.stats.lock().unwrap());
+        let Some((start, end)) = stats.window else {
+            return vec![]
+        };
+        let seconds = end.duration_since(start).as_secs_f64().max(f64::EPSILON);
+        let percentiles = [50.0, 90.0, 99.0, 99.9]
+            .map(|percentile| format_latency(stats.latency.percentile(percentile)))
+            .join(" / ");
+
+        let mut report = vec![
+            (String::from("Disk Workload"), self.description.clone()),
+            (String::from("Read IOPS"), format!("{:.0}", stats.reads as f64 / seconds)),
+            (String::from("Write IOPS"), format!("{:.0}", stats.writes as f64 / seconds)),
+            (String::from("Disk Bandwidth"), format!("{:.1} MB/s", stats.bytes as f64 / seconds / 1e6)),
+            (String::from("Latency p50 / p90 / p99 / p99.9"), percentiles),
+            (String::from("Latency Max"), format_latency(Duration::from_nanos(stats.latency.max))),
+        ];
+        if stats.io_errors > 0 {
+            report.push((String::from("I/O Errors"), stats.io_errors.to_string()));
+        }
+        report
+    }
+}
+
+fn format_latency(latency: Duration) -> String {
+    match latency.as_nanos() {
+        nanos if nanos >= 1_000_000 => format!("{:.2} ms", nanos as f64 / 1e6),
+        nanos if nanos >= 1_000 => format!("{:.1} µs", nanos as f64 / 1e3),
+        nanos => format!("{nanos} ns"),
+    }
+}
+
+/// A heap buffer whose visible part starts on a [`DIRECT_IO_ALIGNMENT`] boundary
+struct AlignedBuffer {
+    bytes: Vec<u8>,
+    start: usize,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    fn new(len: usize) -> Self {
+        let bytes = vec![0u8; len + DIRECT_IO_ALIGNMENT];
+        let start = bytes.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+        AlignedBuffer { bytes, start, len }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[self.start..self.start + self.len]
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.bytes[self.start..self.start + self.len]
+    }
+}
+
+/// The content of `block` after it was written `generation` times
+fn fill_block(buffer: &mut [u8], block: u64, generation: u32) {
+    let mut rng = SplitMix64(SEED ^ block.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ ((generation as u64) << 40));
+    for chunk in buffer.chunks_exact_mut(size_of::<u64>()) {
+        chunk.copy_from_slice(&rng.next().to_le_bytes());
+    }
+}
+
+#[cfg(unix)]
+fn write_block(file: &File, buffer: &[u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.write_all_at(buffer, offset)
+}
+
+#[cfg(unix)]
+fn read_block(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, offset)
+}
+
+#[cfg(not(unix))]
+fn write_block(mut file: &File, buffer: &[u8], offset: u64) -> io::Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(buffer)
+}
+
+#[cfg(not(unix))]
+fn read_block(mut file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buffer)
+}
+
+#[derive(Clone, Copy)]
+struct Operation {
+    block: u64,
+    write: bool,
+}
+
+struct DiskWorker {
+    settings: DiskSettings,
+    file: File,
+    path: PathBuf,
+    /// How often every block was written, including the preparation
+    generations: Vec<u32>,
+    buffer: AlignedBuffer,
+    expected: Vec<u8>,
+    rng: SplitMix64,
+    next_sequential: u64,
+    last: Option<Operation>,
+    last_error: Option<String>,
+    stats: IoStats,
+    shared_stats: Arc<Mutex<IoStats>>,
+}
+
+impl DiskWorker {
+    fn new(settings: DiskSettings, path: PathBuf, shared_stats: Arc<Mutex<IoStats>>, seed: u64) -> Result<DiskWorker, String> {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(true);
+        #[cfg(target_os = "linux")]
+        if settings.direct {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.custom_flags(libc::O_DIRECT);
+        }
+
+        let file = options.open(&path).map_err(|e| match settings.direct {
+            true => format!("Could not open {} for direct I/O, the file system may not support O_DIRECT: {e}", path.display()),
+            false => format!("Could not create {}: {e}", path.display()),
+        })?;
+
+        let blocks = settings.file_size / settings.block_size as u64;
+        Ok(DiskWorker {
+            generations: vec![0; blocks as usize],
+            buffer: AlignedBuffer::new(settings.block_size),
+            expected: vec![0; settings.block_size],
+            rng: SplitMix64(SEED ^ seed),
+            next_sequential: 0,
+            last: None,
+            last_error: None,
+            stats: IoStats::default(),
+            shared_stats,
+            settings,
+            file,
+            path,
+        })
+    }
+
+    /// Writes every block once, so reads never hit a hole in the file
+    fn fill(&mut self, token: &CancellationToken) -> io::Result<()> {
+        for block in 0..self.generations.len() as u64 {
+            if token.is_stopped() {
+                return Ok(())
+            }
+            fill_block(self.buffer.as_mut_slice(), block, 0);
+            write_block(&self.file, self.buffer.as_slice(), block * self.settings.block_size as u64)?;
+        }
+        self.file.sync_all()
+    }
+
+    fn next_block(&mut self) -> u64 {
+        let blocks = self.generations.len() as u64;
+        match self.settings.access {
+            DiskAccess::Sequential => {
+                let block = self.next_sequential;
+                self.next_sequential = (block + 1) % blocks;
+                block
+            }
+            DiskAccess::Random => self.rng.next() % blocks,
+        }
+    }
+
+    fn execute(&mut self, operation: Operation) -> io::Result<()> {
+        let offset = operation.block * self.settings.block_size as u64;
+        if operation.write {
+            let generation = &mut self.generations[operation.block as usize];
+            *generation = generation.wrapping_add(1);
+            fill_block(self.buffer.as_mut_slice(), operation.block, *generation);
+        }
+
+        // only the I/O itself counts towards the latency, not generating the data
+        let started = Instant::now();
+        let result = match operation.write {
+            true => write_block(&self.file, self.buffer.as_slice(), offset)
+                .and_then(|_| if self.settings.fsync { self.file.sync_data() } else { Ok(()) }),
+            false => read_block(&self.file, self.buffer.as_mut_slice(), offset),
+        };
+        let finished = Instant::now();
+        self.stats.latency.record(finished - started);
+        self.stats.window = Some((self.stats.window.map_or(started, |(first, _)| first), finished));
+        result
+    }
+}
+
+impl StressWorker for DiskWorker {
+    fn prepare(&mut self, token: &CancellationToken) -> Result<(), String> {
+        self.fill(token).map_err(|e| format!("Could not prepare {}: {e}", self.path.display()))
+    }
+
+    fn step(&mut self) -> u64 {
+        let block = self.next_block();
+        let write = self.rng.next() % 100 >= self.settings.read_percent as u64;
+        let operation = Operation { block, write };
+
+        match self.execute(operation) {
+            Ok(()) => {
+                match write {
+                    true => self.stats.writes += 1,
+                    false => self.stats.reads += 1,
+                }
+                self.stats.bytes += self.settings.block_size as u64;
+                self.last = Some(operation);
+                self.settings.block_size as u64
+            }
+            Err(e) => {
+                self.stats.io_errors += 1;
+                let kind = if write { "Write" } else { "Read" };
+                self.last_error = Some(format!("{kind} of block {block} failed: {e}"));
+                self.last = None;
+                0
+            }
+        }
+    }
+
+    /// Checks the data of the last read, or reads the last written block back. The read back
+    /// is not counted towards the throughput or latency.
+    fn verify(&mut self) -> Result<(), String> {
+        if let Some(error) = self.last_error.take() {
+            return Err(error)
+        }
+        let Some(operation) = self.last.take() else {
+            return Ok(())
+        };
+
+        if operation.write {
+            let offset = operation.block * self.settings.block_size as u64;
+            read_block(&self.file, self.buffer.as_mut_slice(), offset)
+                .map_err(|e| format!("Reading back block {} failed: {e}", operation.block))?;
+        }
+
+        fill_block(&mut self.expected, operation.block, self.generations[operation.block as usize]);
+        match self.buffer.as_slice().iter().zip(&self.expected).position(|(actual, expected)| actual != expected) {
+            None => Ok(()),
+            Some(offset) => Err(format!("Block {} differs from what was written at byte {offset}", operation.block)),
+        }
+    }
+}
+
+impl Drop for DiskWorker {
+    fn drop(&mut self) {
+        self.shared_stats.lock().unwrap().merge(&self.stats);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_cover_every_latency() {
+        assert_eq!(LatencyHistogram::bucket(u64::MAX), LATENCY_BUCKETS - 1);
+        for bucket in 1..LATENCY_BUCKETS {
+            let floor = LatencyHistogram::bucket_floor(bucket);
+            assert!(floor > LatencyHistogram::bucket_floor(bucket - 1));
+            assert_eq!(LatencyHistogram::bucket(floor), bucket);
+            assert_eq!(LatencyHistogram::bucket(floor - 1), bucket - 1);
+        }
+    }
+
+    #[test]
+    fn percentiles_report_the_bucket_floor() {
+        let mut histogram = LatencyHistogram::default();
+        for micros in 1..=100 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        // 50 µs falls into the bucket that starts at 49.152 µs, about 2% lower
+        let median = LatencyHistogram::bucket_floor(LatencyHistogram::bucket(50_000));
+        assert_eq!(histogram.percentile(50.0), Duration::from_nanos(median));
+        assert!(median <= 50_000 && median as f64 > 50_000.0 * 0.97);
+        assert_eq!(histogram.percentile(0.0), Duration::from_nanos(LatencyHistogram::bucket_floor(LatencyHistogram::bucket(1_000))));
+        // the top bucket is capped at the real maximum
+        assert!(histogram.percentile(100.0) <= Duration::from_micros(100));
+        assert!(histogram.percentile(100.0) > Duration::from_micros(97));
+    }
+
+    #[test]
+    fn huge_latencies_do_not_overflow() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::MAX);
+        assert_eq!(histogram.percentile(99.9), Duration::from_nanos(LatencyHistogram::bucket_floor(LATENCY_BUCKETS - 1)));
+    }
+}
@@ -0,0 +1,172 @@
+use std::hint::black_box;
+use super::{SplitMix64, Stressor, StressWorker};
+
+const VALUES_PER_ITERATION: u64 = 1 << 20;
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Four independent multiply and divide chains, so the multipliers and dividers run side by side
+fn integer_mul_div(seed: u64) -> u64 {
+    let mut rng = SplitMix64(seed);
+    let mut lanes = [1u64, 2, 3, 4];
+    for _ in 0..VALUES_PER_ITERATION / 4 {
+        let value = rng.next();
+        for (lane, accumulator) in lanes.iter_mut().enumerate() {
+            let operand = value.rotate_left(lane as u32 * 16);
+            *accumulator = (accumulator.wrapping_mul(operand | 1) / ((operand >> 40) | 1)).wrapping_add(operand);
+        }
+    }
+    lanes.iter().fold(0, |checksum, lane| checksum ^ lane)
+}
+
+/// Branches on random bits, roughly every other one is mispredicted
+fn unpredictable_branches(seed: u64) -> u64 {
+    let mut rng = SplitMix64(seed);
+    let mut checksum = 0u64;
+    for _ in 0..VALUES_PER_ITERATION {
+        let value = rng.next();
+        if value & 1 == 0 {
+            checksum = checksum.wrapping_add(value >> 3);
+        } else if value & 2 == 0 {
+            checksum ^= value.rotate_left(17);
+        } else if value & 4 == 0 {
+            checksum = checksum.wrapping_mul(value | 1);
+        } else {
+            checksum = checksum.wrapping_sub(value >> 11);
+        }
+        // keeps the compiler from turning the branches into conditional moves
+        checksum = black_box(checksum);
+    }
+    checksum
+}
+
+#[inline(always)]
+fn bit_manipulation_inner(seed: u64) -> u64 {
+    let mut rng = SplitMix64(seed);
+    let mut checksum = 0u64;
+    for _ in 0..VALUES_PER_ITERATION {
+        let value = rng.next();
+        let counts = value.count_ones() as u64 + value.leading_zeros() as u64 + ((value.trailing_zeros() as u64) << 8);
+        checksum = checksum.rotate_left(7) ^ value.swap_bytes() ^ value.reverse_bits().wrapping_add(counts);
+    }
+    checksum
+}
+
+/// popcnt, lzcnt and tzcnt are only emitted when the cpu is known to have them
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "popcnt,lzcnt,bmi1")]
+fn bit_manipulation_native(seed: u64) -> u64 {
+    bit_manipulation_inner(seed)
+}
+
+fn bit_manipulation(seed: u64) -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("popcnt") && is_x86_feature_detected!("lzcnt") && is_x86_feature_detected!("bmi1") {
+        // SAFETY: the features were detected on this cpu
+        return unsafe { bit_manipulation_native(seed) }
+    }
+    bit_manipulation_inner(seed)
+}
+
+/// Integer workloads that keep several execution ports and the branch predictor busy, unlike the
+/// single dependency chains of the float stressors. Every iteration runs on the same seed, so the
+/// checksum computed during setup is what every iteration has to match.
+pub(super) struct ChecksumStressor {
+    name: &'static str,
+    description: &'static str,
+    workload: fn(u64) -> u64,
+}
+
+struct ChecksumWorker {
+    workload: fn(u64) -> u64,
+    expected: u64,
+    last: u64,
+}
+
+impl Stressor for ChecksumStressor {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn verifiable(&self) -> bool {
+        true
+    }
+
+    fn setup(&self) -> Result<Box<dyn StressWorker>, String> {
+        let expected = (self.workload)(black_box(SEED));
+        Ok(Box::new(ChecksumWorker { workload: self.workload, expected, last: expected }))
+    }
+}
+
+impl StressWorker for ChecksumWorker {
+    fn step(&mut self) -> u64 {
+        self.last = (self.workload)(black_box(SEED));
+        1
+    }
+
+    fn verify(&mut self) -> Result<(), String> {
+        match self.last == self.expected {
+            true => Ok(()),
+            false => Err(format!("Checksum {:#018x} does not match {:#018x}", self.last, self.expected)),
+        }
+    }
+}
+
+pub(super) fn integer_stressors() -> Vec<ChecksumStressor> {
+    vec![
+        ChecksumStressor {
+            name: "Integer Multiply/Divide",
+            description: "Independent 64-bit integer multiply and divide chains",
+            workload: integer_mul_div,
+        },
+        ChecksumStressor {
+            name: "Branch Misprediction",
+            description: "Branches on random bits the predictor can not learn",
+            workload: unpredictable_branches,
+        },
+        ChecksumStressor {
+            name: "Bit Manipulation",
+            description: "popcnt, lzcnt, tzcnt, bswap and bit reversal on random values",
+            workload: bit_manipulation,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the checksums of SEED, a change here changes the work every iteration does
+    const KNOWN_CHECKSUMS: [(&str, u64); 3] = [
+        ("Integer Multiply/Divide", 0x7e7d_6e62_eba4_1200),
+        ("Branch Misprediction", 0xeffc_94ec_d014_7d11),
+        ("Bit Manipulation", 0xe32c_d967_d299_9ac7),
+    ];
+
+    #[test]
+    fn workloads_match_their_known_checksums() {
+        let stressors = integer_stressors();
+        assert_eq!(stressors.len(), KNOWN_CHECKSUMS.len());
+        for (stressor, (name, checksum)) in stressors.iter().zip(KNOWN_CHECKSUMS) {
+            assert_eq!(stressor.name, name);
+            assert_eq!((stressor.workload)(SEED), checksum, "{name}");
+        }
+        // the native instructions must not change the result
+        assert_eq!(bit_manipulation_inner(SEED), bit_manipulation(SEED));
+    }
+
+    #[test]
+    fn verification_catches_a_wrong_checksum() {
+        let stressor = &integer_stressors()[0];
+        let mut worker = ChecksumWorker { workload: stressor.workload, expected: KNOWN_CHECKSUMS[0].1, last: 0 };
+        assert_eq!(worker.step(), 1);
+        assert!(worker.verify().is_ok());
+
+        worker.last ^= 1 << 17;
+        let error = worker.verify().unwrap_err();
+        assert!(error.contains("does not match 0x7e7d6e62eba41200"), "{error}");
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::affinity::{CpuTopology, PinningStrategy};
+use crate::job::Job;
+use crate::session::{Component, StressSession};
+use crate::stressors::Stressor;
+
+pub const DEFAULT_SWEEP_DURATION: Duration = Duration::from_secs(60);
+/// What `ezstress sweep` exits with when a core produced errors or could not be tested
+pub const SWEEP_FAILURE_EXIT_CODE: i32 = 5;
+
+/// Runs one verified worker on every logical cpu in turn, so a single unstable core of an
+/// overclocked or undervolted CPU stands out instead of drowning in the load of the others
+pub struct CoreSweep {
+    pub stressor: Arc<dyn Stressor>,
+    /// How long every core is tested
+    pub duration: Duration,
+    /// Logical cpus in the order they are tested
+    pub cpus: Vec<usize>,
+    pub thermal_ceiling: f32,
+}
+
+impl CoreSweep {
+    /// Sweeps every online cpu, see [`CoreSweep::only`] to test some of them
+    pub fn new(stressor: Arc<dyn Stressor>, duration: Duration, thermal_ceiling: f32) -> Result<CoreSweep, String> {
+        if !stressor.runs_on_cpu() {
+            return Err(format!("{stressor} does not run on the CPU"))
+        }
+        if !stressor.verifiable() {
+            return Err(format!("{stressor} can not verify its results, a sweep needs a verifiable stressor"))
+        }
+
+        let topology = CpuTopology::detect()?;
+        Ok(CoreSweep {
+            stressor,
+            duration,
+            cpus: topology.cpus.iter().map(|cpu| cpu.id).collect(),
+            thermal_ceiling,
+        })
+    }
+
+    /// Restricts the sweep to these cpus, in the given order
+    pub fn only(&mut self, cpus: Vec<usize>) -> Result<(), String> {
+        if cpus.is_empty() {
+            return Err("At least one CPU is required".to_string())
+        }
+        if let Some(missing) = cpus.iter().find(|cpu| !self.cpus.contains(cpu)) {
+            return Err(format!("CPU {missing} does not exist or is offline"))
+        }
+        self.cpus = cpus;
+        Ok(())
+    }
+
+    /// A single verified worker pinned to `cpu`
+    pub fn session(&self, cpu: usize) -> Result<StressSession, String> {
+        StressSession::builder()
+            .component(Component::Cpu)
+            .stressor(self.stressor.clone())
+            .threads(1)
+            .pinning(PinningStrategy::Explicit(vec![cpu]))
+            .verify(true)
+            .duration(self.duration)
+            .thermal_ceiling(self.thermal_ceiling)
+            .build()
+    }
+}
+
+pub struct CoreResult {
+    pub cpu: usize,
+    /// False when the OS did not let the worker run on `cpu`, the result then belongs to
+    /// whichever cores it was scheduled on
+    pub pinned: bool,
+    pub throughput: f64,
+    pub max_cpu_temp: Option<f32>,
+    pub errors: u64,
+}
+
+impl CoreResult {
+    pub fn new(cpu: usize, job: &Job) -> CoreResult {
+        CoreResult {
+            cpu,
+            pinned: !job.unpinned_cpus.contains(&cpu),
+            throughput: job.throughput(),
+            max_cpu_temp: job.max_cpu_temp,
+            errors: job.errors_detected.unwrap_or(0),
+        }
+    }
+
+    /// Only verification errors count, a slow core can still be a stable one. A core the worker
+    /// could not be pinned to was never tested and is not stable either.
+    pub fn stable(&self) -> bool {
+        self.pinned && self.errors == 0
+    }
+
+    /// Throughput relative to the fastest pinned core of the sweep, in percent
+    pub fn relative_throughput(&self, results: &[CoreResult]) -> f64 {
+        let fastest = results.iter()
+            .filter(|result| result.pinned)
+            .map(|result| result.throughput)
+            .fold(0.0, f64::max);
+        match fastest {
+            fastest if fastest > 0.0 => self.throughput / fastest * 100.0,
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(cpu: usize, pinned: bool, throughput: f64, errors: u64) -> CoreResult {
+        CoreResult { cpu, pinned, throughput, max_cpu_temp: None, errors }
+    }
+
+    #[test]
+    fn unpinned_cores_are_not_stable() {
+        assert!(result(0, true, 100.0, 0).stable());
+        assert!(!result(0, true, 100.0, 1).stable());
+        assert!(!result(0, false, 100.0, 0).stable());
+    }
+
+    #[test]
+    fn unpinned_cores_do_not_set_the_fastest_throughput() {
+        let results = [result(0, true, 50.0, 0), result(1, true, 100.0, 0), result(2, false, 400.0, 0)];
+        assert_eq!(results[0].relative_throughput(&results), 50.0);
+        assert_eq!(results[1].relative_throughput(&results), 100.0);
+    }
+}
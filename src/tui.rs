@@ -0,0 +1,339 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+use ratatui::{DefaultTerminal, Frame};
+use ratatui::crossterm::event::{self as terminal_event, Event as TerminalEvent, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph, Sparkline};
+use ezstress::CancellationToken;
+use ezstress::events::{Event, Sample, Stage};
+use ezstress::job::pretty_print_int;
+use ezstress::sensors::{self, CoreLoad};
+
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// samples arrive every 500ms, so this keeps the last two minutes on screen
+const HISTORY_LENGTH: usize = 240;
+const LOG_LENGTH: usize = 50;
+
+/// Full-screen dashboard for a single run. Owns the terminal until the run finishes or the event
+/// stream disconnects, errors are printed again afterwards so they survive leaving the screen.
+pub fn run_dashboard(events: Receiver<Event>, token: CancellationToken) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let mut dashboard = Dashboard::new(token);
+    let result = dashboard.run(&mut terminal, events);
+    ratatui::restore();
+
+    for error in &dashboard.errors {
+        println!("{}", colored::Colorize::red(error.as_str()));
+    }
+
+    result
+}
+
+struct Dashboard {
+    token: CancellationToken,
+    stressor: Option<String>,
+    unit: String,
+    threads: Option<usize>,
+    stage: Option<Stage>,
+    latest: Option<Sample>,
+    /// What the frequency bars are scaled to, in MHz. The rated maximum, or the highest
+    /// frequency seen so far when the OS does not report one.
+    max_frequency: u64,
+    sensor_history: BTreeMap<String, VecDeque<u64>>,
+    throughput_history: VecDeque<(f64, f64)>,
+    log: VecDeque<Line<'static>>,
+    errors: Vec<String>,
+}
+
+impl Dashboard {
+    fn new(token: CancellationToken) -> Self {
+        Dashboard {
+            token,
+            stressor: None,
+            unit: String::from("iterations"),
+            threads: None,
+            stage: None,
+            latest: None,
+            max_frequency: sensors::max_core_frequency().unwrap_or(0),
+            sensor_history: BTreeMap::new(),
+            throughput_history: VecDeque::new(),
+            log: VecDeque::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal, events: Receiver<Event>) -> io::Result<()> {
+        loop {
+            loop {
+                match events.try_recv() {
+                    Ok(Event::Finished(_)) | Err(TryRecvError::Disconnected) => return Ok(()),
+                    Ok(event) => self.handle_event(event),
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if terminal_event::poll(INPUT_POLL_INTERVAL)? {
+                if let TerminalEvent::Key(key) = terminal_event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue
+                    }
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => {
+                            self.token.cancel();
+                        }
+                        // raw mode swallows the signal, so Control+C arrives as a key press
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.token.cancel();
+                        }
+                        KeyCode::Char('p') | KeyCode::Char(' ') => {
+                            if self.token.is_paused() {
+                                self.token.resume();
+                            } else {
+                                self.token.pause();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Started { stressor, unit, threads } => {
+                self.stressor = Some(stressor);
+                self.unit = unit;
+                self.threads = threads;
+                // the baseline samples do not belong on the load graph
+                self.throughput_history.clear();
+            }
+            Event::StageChanged(stage) => self.stage = Some(stage),
+            Event::Sample(sample) => self.record_sample(sample),
+            Event::Warning(warning) => self.push_log(Line::from(format!("⚠ {warning}")).yellow()),
+            Event::Error(error) => {
+                self.push_log(Line::from(error.clone()).red());
+                self.errors.push(error);
+            }
+            Event::Finished(_) => {}
+        }
+    }
+
+    fn record_sample(&mut self, sample: Sample) {
+        for reading in &sample.temperatures {
+            let history = self.sensor_history.entry(reading.label.clone()).or_default();
+            push_bounded(history, reading.temperature.max(0.0) as u64, HISTORY_LENGTH);
+        }
+
+        let fastest = sample.cores.iter().map(|core| core.frequency).max().unwrap_or(0);
+        self.max_frequency = self.max_frequency.max(fastest);
+
+        if self.stage == Some(Stage::Load) {
+            push_bounded(&mut self.throughput_history, (sample.elapsed.as_secs_f64(), sample.throughput), HISTORY_LENGTH);
+        }
+
+        self.latest = Some(sample);
+    }
+
+    fn push_log(&mut self, line: Line<'static>) {
+        push_bounded(&mut self.log, line, LOG_LENGTH);
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, log] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(8),
+            Constraint::Length(5),
+        ]).areas(frame.area());
+        let [left, right] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(body);
+        let [sensors, throughput] = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(right);
+
+        self.draw_header(frame, header);
+        match self.threads {
+            Some(_) => self.draw_cores(frame, left),
+            None => {
+                let [gpu, cores] = Layout::vertical([Constraint::Length(5), Constraint::Min(3)]).areas(left);
+                self.draw_gpu(frame, gpu);
+                self.draw_cores(frame, cores);
+            }
+        }
+        self.draw_sensors(frame, sensors);
+        self.draw_throughput(frame, throughput);
+
+        let visible = log.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.log.iter().skip(self.log.len().saturating_sub(visible)).cloned().collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Log ")), log);
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let stage = match self.stage {
+            Some(Stage::IdleBaseline) => "Measuring idle baseline",
+            Some(Stage::Prepare) => "Preparing",
+            Some(Stage::Load) => "Under load",
+            Some(Stage::Cooldown) => "Cooling down",
+            None => "Starting",
+        };
+
+        let mut spans = vec![
+            Span::from(self.stressor.clone().unwrap_or_default()).bold(),
+            Span::from(format!("  {stage}")),
+        ];
+
+        if let Some(sample) = &self.latest {
+            spans.push(Span::from(format!("  🕛 {}", format_duration(sample.elapsed))));
+            if let Some(remaining) = sample.remaining {
+                spans.push(Span::from(format!(" ({} left)", format_duration(remaining))));
+            }
+            if let Some(temp) = sample.cpu_temp {
+                spans.push(Span::from("  🌡️ "));
+                spans.push(Span::styled(format!("{temp:.1}°C"), Style::new().fg(temperature_color(temp))));
+            }
+        }
+
+        if self.token.is_paused() {
+            spans.push(Span::from("  PAUSED").yellow().bold());
+        }
+
+        let block = Block::bordered().title(" ezstress ").title_bottom(Line::from(" q: stop  p: pause/resume ").right_aligned());
+        frame.render_widget(Paragraph::new(Line::from(spans)).block(block), area);
+    }
+
+    fn draw_cores(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" CPU cores ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let cores: &[CoreLoad] = self.latest.as_ref().map_or(&[], |sample| &sample.cores);
+        if cores.is_empty() || inner.height == 0 {
+            return
+        }
+
+        // spread the cores over as many columns as it takes to fit them all
+        let columns = cores.len().div_ceil(inner.height as usize);
+        let column_areas = Layout::horizontal(vec![Constraint::Ratio(1, columns as u32); columns]).split(inner);
+
+        for (column, chunk) in cores.chunks(inner.height as usize).enumerate() {
+            let area = column_areas[column];
+            let offset = column * inner.height as usize;
+            // "99 100% 9999MHz" plus spacing, whatever is left is split between the load and
+            // the frequency bar
+            let bar_width = ((area.width as usize).saturating_sub(18) / 2).max(1);
+            let lines: Vec<Line> = chunk.iter().enumerate().map(|(index, core)| {
+                let frequency = core.frequency as f32 / self.max_frequency.max(1) as f32;
+                let mut line = vec![Span::from(format!("{:>2} ", offset + index))];
+                line.extend(bar(core.usage / 100.0, bar_width, load_color(core.usage)));
+                line.push(Span::from(format!(" {:>3.0}% ", core.usage)));
+                line.extend(bar(frequency, bar_width, Color::Cyan));
+                line.push(Span::from(format!(" {:>4}MHz", core.frequency)));
+                Line::from(line)
+            }).collect();
+            frame.render_widget(Paragraph::new(lines), area);
+        }
+    }
+
+    fn draw_gpu(&self, frame: &mut Frame, area: Rect) {
+        let (work_done, throughput) = self.latest.as_ref()
+            .map_or((0, 0.0), |sample| (sample.work_done, sample.throughput));
+        let lines = vec![
+            Line::from(format!("Work done: {} {}", pretty_print_int(work_done), self.unit)),
+            Line::from(format!("Throughput: {} {}/s", pretty_print_int(throughput as u64), self.unit)),
+        ];
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" GPU ")), area);
+    }
+
+    fn draw_sensors(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Temperatures ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let latest = self.latest.as_ref().map_or(&[][..], |sample| &sample.temperatures[..]);
+        let label_width = self.sensor_history.keys().map(|label| label.len()).max().unwrap_or(0).min(24) as u16 + 10;
+
+        let rows = Layout::vertical(vec![Constraint::Length(1); inner.height as usize]).split(inner);
+        for ((label, history), row) in self.sensor_history.iter().zip(rows.iter()) {
+            let [label_area, graph_area] = Layout::horizontal([Constraint::Length(label_width), Constraint::Min(1)]).areas(*row);
+            let current = latest.iter().find(|reading| &reading.label == label).map(|reading| reading.temperature);
+            let color = current.map_or(Color::DarkGray, temperature_color);
+            let text = match current {
+                Some(temp) => format!("{label:.24} {temp:>5.1}°C"),
+                None => format!("{label:.24}"),
+            };
+            frame.render_widget(Paragraph::new(text), label_area);
+
+            // newest readings on the right, as many as fit
+            let visible: Vec<u64> = history.iter().skip(history.len().saturating_sub(graph_area.width as usize)).copied().collect();
+            let sparkline = Sparkline::default()
+                .data(&visible)
+                .max(history.iter().copied().max().unwrap_or(100).max(100))
+                .style(Style::new().fg(color));
+            frame.render_widget(sparkline, graph_area);
+        }
+    }
+
+    fn draw_throughput(&self, frame: &mut Frame, area: Rect) {
+        let points: Vec<(f64, f64)> = self.throughput_history.iter().copied().collect();
+        let min_x = points.first().map_or(0.0, |point| point.0);
+        let max_x = points.last().map_or(1.0, |point| point.0).max(min_x + 1.0);
+        let max_y = points.iter().map(|point| point.1).fold(0.0, f64::max).max(1.0) * 1.1;
+
+        let dataset = Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::new().fg(Color::Cyan))
+            .data(&points);
+
+        let chart = Chart::new(vec![dataset])
+            .block(Block::bordered().title(format!(" Throughput ({}/s) ", self.unit)))
+            .x_axis(Axis::default()
+                .bounds([min_x, max_x])
+                .labels([format!("{min_x:.0}s"), format!("{max_x:.0}s")]))
+            .y_axis(Axis::default()
+                .bounds([0.0, max_y])
+                .labels([String::from("0"), pretty_print_int(max_y as u64)]));
+        frame.render_widget(chart, area);
+    }
+}
+
+fn push_bounded<T>(queue: &mut VecDeque<T>, value: T, length: usize) {
+    if queue.len() == length {
+        queue.pop_front();
+    }
+    queue.push_back(value);
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+}
+
+fn temperature_color(temp: f32) -> Color {
+    match temp {
+        temp if temp > 80.0 => Color::Red,
+        temp if temp > 60.0 => Color::Yellow,
+        _ => Color::Green,
+    }
+}
+
+/// `share` of `width` filled in `color`, the rest greyed out
+fn bar(share: f32, width: usize, color: Color) -> [Span<'static>; 2] {
+    let filled = ((share.clamp(0.0, 1.0) * width as f32).round() as usize).min(width);
+    [
+        Span::styled("█".repeat(filled), Style::new().fg(color)),
+        Span::styled("░".repeat(width - filled), Style::new().fg(Color::DarkGray)),
+    ]
+}
+
+fn load_color(usage: f32) -> Color {
+    match usage {
+        usage if usage > 90.0 => Color::Red,
+        usage if usage > 50.0 => Color::Yellow,
+        _ => Color::Green,
+    }
+}
@@ -0,0 +1,60 @@
+use std::thread;
+use std::time::Duration;
+use sysinfo::{System, SystemExt};
+use serde::{Deserialize, Serialize};
+use crate::control::{CancellationToken, StopReason};
+use crate::sensors;
+
+// used for sensors that do not report their own critical threshold
+pub const DEFAULT_THERMAL_CEILING: f32 = 100.0;
+pub const THERMAL_CUTOFF_EXIT_CODE: i32 = 3;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThermalTrip {
+    pub label: String,
+    pub temperature: f32,
+    pub threshold: f32,
+}
+
+impl std::fmt::Display for ThermalTrip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} reached {:.2}°C (limit {:.2}°C)", self.label, self.temperature, self.threshold)
+    }
+}
+
+/// Polls every temperature sensor until the run stops. If any sensor crosses its critical
+/// threshold (or `ceiling` when it has none) all workers are stopped with
+/// [`StopReason::ThermalCutoff`] regardless of the termination methods the user picked.
+pub fn watch_thermals(
+    ceiling: f32,
+    token: &CancellationToken,
+) -> Option<ThermalTrip> {
+    let mut system = System::new();
+    system.refresh_components_list();
+
+    while !token.is_stopped() {
+        system.refresh_components();
+
+        let trip = sensors::all_temps(&mut system, false)
+            .into_iter()
+            .find_map(|reading| {
+                let threshold = reading.critical.map_or(ceiling, |crit| crit.min(ceiling));
+                (reading.temperature >= threshold).then_some(ThermalTrip {
+                    label: reading.label,
+                    temperature: reading.temperature,
+                    threshold,
+                })
+            });
+
+        if let Some(trip) = trip {
+            // only claim the stop if nothing else stopped the run first
+            return token.stop(StopReason::ThermalCutoff).then_some(trip);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    None
+}
@@ -0,0 +1,256 @@
+use std::io::Write;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+use crate::events::{Event, Sample, Stage};
+use crate::job::Job;
+
+const INDEX_HTML: &str = include_str!("dashboard.html");
+
+#[derive(Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RunState {
+    #[default]
+    Idle,
+    Running,
+    Finished,
+}
+
+/// Everything known about the current (or last) run, served as `/status`
+#[derive(Default, Serialize)]
+struct RunStatus {
+    state: RunState,
+    stressor: Option<String>,
+    unit: Option<String>,
+    threads: Option<usize>,
+    stage: Option<Stage>,
+    sample: Option<Sample>,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+    job: Option<Job>,
+}
+
+#[derive(Default)]
+struct Shared {
+    status: Mutex<RunStatus>,
+    // one sender per open `/events` stream, each already formatted as a server-sent event
+    streams: Mutex<Vec<Sender<String>>>,
+}
+
+impl Shared {
+    fn broadcast<T: Serialize>(&self, name: &str, payload: &T) {
+        let Ok(data) = serde_json::to_string(payload) else {
+            return
+        };
+        let message = format!("event: {name}\ndata: {data}\n\n");
+        self.streams.lock().unwrap().retain(|stream| stream.send(message.clone()).is_ok());
+    }
+}
+
+/// Small HTTP server for watching runs from a browser. `/` serves a page with live temperature
+/// and throughput charts, `/events` streams every run event as server-sent events and `/status`
+/// returns the current run, including the finished [`Job`], as JSON.
+///
+/// The server outlives single runs, [`WebDashboard::attach`] it to the events of every session.
+/// There is no authentication, so keep it on a loopback address unless the network is trusted.
+pub struct WebDashboard {
+    server: Arc<Server>,
+    shared: Arc<Shared>,
+}
+
+impl WebDashboard {
+    pub fn start(address: impl ToSocketAddrs) -> Result<WebDashboard, String> {
+        let server = Server::http(address)
+            .map(Arc::new)
+            .map_err(|e| format!("Could not start the web dashboard: {e}"))?;
+        let shared = Arc::new(Shared::default());
+
+        let accept_server = server.clone();
+        let accept_shared = shared.clone();
+        thread::spawn(move || {
+            for request in accept_server.incoming_requests() {
+                handle_request(request, &accept_shared);
+            }
+        });
+
+        Ok(WebDashboard { server, shared })
+    }
+
+    /// The address the server is bound to, useful when it was started on port 0
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Feeds the events of a session to the dashboard until that run finishes
+    pub fn attach(&self, events: Receiver<Event>) -> JoinHandle<()> {
+        let mut status = self.shared.status.lock().unwrap();
+        *status = RunStatus { state: RunState::Running, ..RunStatus::default() };
+        // tells open pages to clear the charts of the previous run
+        self.shared.broadcast("status", &*status);
+        drop(status);
+        let shared = self.shared.clone();
+
+        thread::spawn(move || {
+            for event in events {
+                let mut status = shared.status.lock().unwrap();
+                match event {
+                    Event::Started { stressor, unit, threads } => {
+                        status.stressor = Some(stressor);
+                        status.unit = Some(unit);
+                        status.threads = threads;
+                        shared.broadcast("status", &*status);
+                    }
+                    Event::StageChanged(stage) => {
+                        status.stage = Some(stage);
+                        shared.broadcast("stage", &stage);
+                    }
+                    Event::Sample(sample) => {
+                        shared.broadcast("sample", &sample);
+                        status.sample = Some(sample);
+                    }
+                    Event::Warning(warning) => {
+                        shared.broadcast("warning", &warning);
+                        status.warnings.push(warning);
+                    }
+                    Event::Error(error) => {
+                        shared.broadcast("error", &error);
+                        status.errors.push(error);
+                    }
+                    Event::Finished(job) => {
+                        status.state = RunState::Finished;
+                        status.job = Some((*job).clone());
+                        shared.broadcast("status", &*status);
+                        break
+                    }
+                }
+            }
+
+            // a run that failed to start never sends Finished
+            let mut status = shared.status.lock().unwrap();
+            if let RunState::Running = status.state {
+                status.state = RunState::Finished;
+                shared.broadcast("status", &*status);
+            }
+        })
+    }
+}
+
+impl Drop for WebDashboard {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+fn handle_request(request: Request, shared: &Arc<Shared>) {
+    if *request.method() != Method::Get {
+        let _ = request.respond(Response::empty(405));
+        return
+    }
+
+    let path = request.url().split('?').next().unwrap_or_default().to_string();
+    let _ = match path.as_str() {
+        "/" | "/index.html" => request.respond(
+            Response::from_string(INDEX_HTML).with_header(content_type("text/html; charset=utf-8"))
+        ),
+        "/status" => {
+            let body = serde_json::to_string(&*shared.status.lock().unwrap()).unwrap_or_default();
+            request.respond(Response::from_string(body).with_header(content_type("application/json")))
+        }
+        "/events" => {
+            let (sender, receiver) = channel();
+            shared.streams.lock().unwrap().push(sender);
+            // streams stay open for as long as the browser does, so they get their own thread
+            thread::spawn(move || stream_events(request, receiver));
+            Ok(())
+        }
+        _ => request.respond(Response::from_string("Not Found").with_status_code(404)),
+    };
+}
+
+fn stream_events(request: Request, messages: Receiver<String>) {
+    // tiny_http buffers chunked bodies, so the response is written by hand to flush every event
+    let mut writer = request.into_writer();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
+    if writer.write_all(head.as_bytes()).and_then(|_| writer.flush()).is_err() {
+        return
+    }
+
+    for message in messages {
+        if writer.write_all(message.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            // the receiver drops here, so the next broadcast forgets this stream
+            return
+        }
+    }
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpStream;
+    use std::time::Duration;
+    use crate::events::EventBus;
+    use super::*;
+
+    fn get(dashboard: &WebDashboard, path: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(dashboard.address().unwrap()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        stream
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            elapsed: Duration::from_secs(1),
+            remaining: None,
+            cpu_temp: Some(55.0),
+            temperatures: vec![],
+            cores: vec![],
+            work_done: 42,
+            workers: vec![],
+            throughput: 42.0,
+        }
+    }
+
+    #[test]
+    fn status_is_json() {
+        let dashboard = WebDashboard::start("127.0.0.1:0").unwrap();
+        let bus = EventBus::default();
+        let _feed = dashboard.attach(bus.subscribe());
+
+        let mut response = String::new();
+        get(&dashboard, "/status").read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert!(head.contains("application/json"), "{head}");
+        let status: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(status["state"], "running");
+    }
+
+    #[test]
+    fn events_stream_samples() {
+        let dashboard = WebDashboard::start("127.0.0.1:0").unwrap();
+        let bus = EventBus::default();
+        let _feed = dashboard.attach(bus.subscribe());
+
+        let mut events = BufReader::new(get(&dashboard, "/events"));
+        let mut line = String::new();
+        events.read_line(&mut line).unwrap();
+        assert!(line.starts_with("HTTP/1.1 200"), "{line}");
+        // the stream is registered before its head is written, so the sample can not be missed
+        bus.emit(Event::Sample(sample()));
+
+        let data = events.lines()
+            .map(Result::unwrap)
+            .find(|line| line.starts_with("data:"))
+            .unwrap();
+        let sample: serde_json::Value = serde_json::from_str(data.trim_start_matches("data:").trim()).unwrap();
+        assert_eq!(sample["work_done"], 42);
+    }
+}